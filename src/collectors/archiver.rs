@@ -0,0 +1,100 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct StatArchiver {
+    archived_count: i64,
+    failed_count: i64,
+    // NULL when nothing was archived yet or the file is a timeline history.
+    last_archived_segment: Option<i64>,
+    last_archived_time: Option<f64>,
+    last_failed_segment: Option<i64>,
+    last_failed_time: Option<f64>,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // WAL file names are a timeline followed by the "log" and segment
+    // halves of the position, each as 8 hex digits; the segment number
+    // derived from them grows by one per file without labelling series
+    // with the file name.
+    let stat_archiver_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                archived_count,
+                failed_count,
+                CASE WHEN last_archived_wal ~ '^[0-9A-F]{24}' THEN
+                    ('x' || substr(last_archived_wal, 9, 8))::bit(32)::bigint * segments_per_log
+                    + ('x' || substr(last_archived_wal, 17, 8))::bit(32)::bigint
+                END last_archived_segment,
+                extract(epoch FROM last_archived_time)::float8 last_archived_time,
+                CASE WHEN last_failed_wal ~ '^[0-9A-F]{24}' THEN
+                    ('x' || substr(last_failed_wal, 9, 8))::bit(32)::bigint * segments_per_log
+                    + ('x' || substr(last_failed_wal, 17, 8))::bit(32)::bigint
+                END last_failed_segment,
+                extract(epoch FROM last_failed_time)::float8 last_failed_time
+            FROM
+                pg_stat_archiver,
+                LATERAL (SELECT 4294967296 / (setting::bigint
+                    * CASE unit WHEN '8kB' THEN 8192 ELSE 1 END) segments_per_log
+                    FROM pg_settings WHERE name = 'wal_segment_size') wal_segment")
+        .await?;
+    let row = postgres_client
+        .query_one(&stat_archiver_statement, &[])
+        .await?;
+    let stat_archiver = StatArchiver {
+        archived_count: row.try_get(0)?,
+        failed_count: row.try_get(1)?,
+        last_archived_segment: row.try_get(2)?,
+        last_archived_time: row.try_get(3)?,
+        last_failed_segment: row.try_get(4)?,
+        last_failed_time: row.try_get(5)?,
+    };
+    let mut archived_count = MetricFamily::counter(
+        "pg_stat_archiver_archived_count",
+        "Number of WAL files that have been successfully archived",
+    );
+    archived_count.sample(&[], stat_archiver.archived_count as f64);
+    let mut failed_count = MetricFamily::counter(
+        "pg_stat_archiver_failed_count",
+        "Number of failed attempts for archiving WAL files",
+    );
+    failed_count.sample(&[], stat_archiver.failed_count as f64);
+    let mut last_archived_segment = MetricFamily::gauge(
+        "pg_stat_archiver_last_archived_segment",
+        "Number of the WAL segment last archived successfully",
+    );
+    if let Some(value) = stat_archiver.last_archived_segment {
+        last_archived_segment.sample(&[], value as f64);
+    }
+    let mut last_archived_time = MetricFamily::gauge(
+        "pg_stat_archiver_last_archived_time_seconds",
+        "Time of the last successful archive operation",
+    )
+    .with_unit("seconds");
+    if let Some(value) = stat_archiver.last_archived_time {
+        last_archived_time.sample(&[], value);
+    }
+    let mut last_failed_segment = MetricFamily::gauge(
+        "pg_stat_archiver_last_failed_segment",
+        "Number of the WAL segment whose archiving failed last",
+    );
+    if let Some(value) = stat_archiver.last_failed_segment {
+        last_failed_segment.sample(&[], value as f64);
+    }
+    let mut last_failed_time = MetricFamily::gauge(
+        "pg_stat_archiver_last_failed_time_seconds",
+        "Time of the last failed archive operation",
+    )
+    .with_unit("seconds");
+    if let Some(value) = stat_archiver.last_failed_time {
+        last_failed_time.sample(&[], value);
+    }
+    Ok(vec![
+        archived_count,
+        failed_count,
+        last_archived_segment,
+        last_archived_time,
+        last_failed_segment,
+        last_failed_time,
+    ])
+}
@@ -0,0 +1,139 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct StatBgwriter {
+    checkpoints_timed: i64,
+    checkpoints_req: i64,
+    checkpoint_write_time: f64,
+    checkpoint_sync_time: f64,
+    buffers_checkpoint: i64,
+    buffers_clean: i64,
+    maxwritten_clean: i64,
+    // Before PostgreSQL 17, which moved them to pg_stat_io.
+    buffers_backend: Option<i64>,
+    buffers_backend_fsync: Option<i64>,
+    buffers_alloc: i64,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // PostgreSQL 17 split the checkpointer columns out of pg_stat_bgwriter
+    // into pg_stat_checkpointer; they are exported under the old names.
+    let query = if server_version >= 170000 {
+        "SELECT
+                c.num_timed,
+                c.num_requested,
+                c.write_time,
+                c.sync_time,
+                c.buffers_written,
+                b.buffers_clean,
+                b.maxwritten_clean,
+                NULL::bigint,
+                NULL::bigint,
+                b.buffers_alloc
+            FROM
+                pg_stat_bgwriter b,
+                pg_stat_checkpointer c"
+    } else {
+        "SELECT
+                checkpoints_timed,
+                checkpoints_req,
+                checkpoint_write_time,
+                checkpoint_sync_time,
+                buffers_checkpoint,
+                buffers_clean,
+                maxwritten_clean,
+                buffers_backend,
+                buffers_backend_fsync,
+                buffers_alloc
+            FROM
+                pg_stat_bgwriter"
+    };
+    let stat_bgwriter_statement = postgres_client.prepare_cached(query).await?;
+    let row = postgres_client
+        .query_one(&stat_bgwriter_statement, &[])
+        .await?;
+    let stat_bgwriter = StatBgwriter {
+        checkpoints_timed: row.try_get(0)?,
+        checkpoints_req: row.try_get(1)?,
+        checkpoint_write_time: row.try_get(2)?,
+        checkpoint_sync_time: row.try_get(3)?,
+        buffers_checkpoint: row.try_get(4)?,
+        buffers_clean: row.try_get(5)?,
+        maxwritten_clean: row.try_get(6)?,
+        buffers_backend: row.try_get(7)?,
+        buffers_backend_fsync: row.try_get(8)?,
+        buffers_alloc: row.try_get(9)?,
+    };
+    let mut checkpoints_timed = MetricFamily::counter(
+        "pg_stat_bgwriter_checkpoints_timed",
+        "Number of scheduled checkpoints that have been performed",
+    );
+    checkpoints_timed.sample(&[], stat_bgwriter.checkpoints_timed as f64);
+    let mut checkpoints_req = MetricFamily::counter(
+        "pg_stat_bgwriter_checkpoints_req",
+        "Number of requested checkpoints that have been performed",
+    );
+    checkpoints_req.sample(&[], stat_bgwriter.checkpoints_req as f64);
+    let mut checkpoint_write_time = MetricFamily::counter(
+        "pg_stat_bgwriter_checkpoint_write_time_seconds",
+        "Time spent in the portion of checkpoint processing where files are written to disk",
+    )
+    .with_unit("seconds");
+    checkpoint_write_time.sample(&[], stat_bgwriter.checkpoint_write_time / 1000.0);
+    let mut checkpoint_sync_time = MetricFamily::counter(
+        "pg_stat_bgwriter_checkpoint_sync_time_seconds",
+        "Time spent in the portion of checkpoint processing where files are synchronized to disk",
+    )
+    .with_unit("seconds");
+    checkpoint_sync_time.sample(&[], stat_bgwriter.checkpoint_sync_time / 1000.0);
+    let mut buffers_checkpoint = MetricFamily::counter(
+        "pg_stat_bgwriter_buffers_checkpoint",
+        "Number of buffers written during checkpoints",
+    );
+    buffers_checkpoint.sample(&[], stat_bgwriter.buffers_checkpoint as f64);
+    let mut buffers_clean = MetricFamily::counter(
+        "pg_stat_bgwriter_buffers_clean",
+        "Number of buffers written by the background writer",
+    );
+    buffers_clean.sample(&[], stat_bgwriter.buffers_clean as f64);
+    let mut maxwritten_clean = MetricFamily::counter(
+        "pg_stat_bgwriter_maxwritten_clean",
+        "Number of times the background writer stopped a cleaning scan because it had written too many buffers",
+    );
+    maxwritten_clean.sample(&[], stat_bgwriter.maxwritten_clean as f64);
+    let mut buffers_backend = MetricFamily::counter(
+        "pg_stat_bgwriter_buffers_backend",
+        "Number of buffers written directly by a backend",
+    );
+    if let Some(value) = stat_bgwriter.buffers_backend {
+        buffers_backend.sample(&[], value as f64);
+    }
+    let mut buffers_backend_fsync = MetricFamily::counter(
+        "pg_stat_bgwriter_buffers_backend_fsync",
+        "Number of times a backend had to execute its own fsync call",
+    );
+    if let Some(value) = stat_bgwriter.buffers_backend_fsync {
+        buffers_backend_fsync.sample(&[], value as f64);
+    }
+    let mut buffers_alloc = MetricFamily::counter(
+        "pg_stat_bgwriter_buffers_alloc",
+        "Number of buffers allocated",
+    );
+    buffers_alloc.sample(&[], stat_bgwriter.buffers_alloc as f64);
+    Ok(vec![
+        checkpoints_timed,
+        checkpoints_req,
+        checkpoint_write_time,
+        checkpoint_sync_time,
+        buffers_checkpoint,
+        buffers_clean,
+        maxwritten_clean,
+        buffers_backend,
+        buffers_backend_fsync,
+        buffers_alloc,
+    ])
+}
@@ -0,0 +1,250 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct CommonEffectiveness {
+    schemaname: String,
+    relname: String,
+    seq_scan: i64,
+    seq_tup_read: i64,
+    idx_scan: Option<i64>,
+    avg: Option<i64>,
+    n_tup_ins: i64,
+    n_tup_upd: i64,
+    n_tup_del: i64,
+    n_tup_hot_upd: i64,
+    n_live_tup: i64,
+    n_dead_tup: i64,
+    n_mod_since_analyze: i64,
+    // PostgreSQL 13+.
+    n_ins_since_vacuum: Option<i64>,
+    last_vacuum: Option<f64>,
+    last_autovacuum: Option<f64>,
+    last_analyze: Option<f64>,
+    last_autoanalyze: Option<f64>,
+    vacuum_count: i64,
+    autovacuum_count: i64,
+    analyze_count: i64,
+    autoanalyze_count: i64,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let n_ins_since_vacuum_column = if server_version >= 130000 {
+        "n_ins_since_vacuum"
+    } else {
+        "NULL::bigint"
+    };
+    let common_effectiveness_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                schemaname,
+                relname,
+                seq_scan,
+                seq_tup_read,
+                idx_scan,
+                vacuum_count,
+                autovacuum_count,
+                analyze_count,
+                autoanalyze_count,
+                seq_tup_read / nullif(seq_scan, 0) as avg,
+                n_tup_ins,
+                n_tup_upd,
+                n_tup_del,
+                n_tup_hot_upd,
+                n_live_tup,
+                n_dead_tup,
+                n_mod_since_analyze,
+                {},
+                extract(epoch FROM last_vacuum)::float8 last_vacuum,
+                extract(epoch FROM last_autovacuum)::float8 last_autovacuum,
+                extract(epoch FROM last_analyze)::float8 last_analyze,
+                extract(epoch FROM last_autoanalyze)::float8 last_autoanalyze
+            FROM
+                pg_stat_user_tables
+            ORDER BY seq_tup_read DESC",
+            n_ins_since_vacuum_column
+        ))
+        .await?;
+    let common_effectiveness_rows = postgres_client
+        .query(&common_effectiveness_statement, &[])
+        .await?;
+    let common_effectiveness = common_effectiveness_rows
+        .iter()
+        .map(|row| {
+            Ok(CommonEffectiveness {
+                schemaname: row.try_get(0)?,
+                relname: row.try_get(1)?,
+                seq_scan: row.try_get(2)?,
+                seq_tup_read: row.try_get(3)?,
+                idx_scan: row.try_get(4)?,
+                vacuum_count: row.try_get(5)?,
+                autovacuum_count: row.try_get(6)?,
+                analyze_count: row.try_get(7)?,
+                autoanalyze_count: row.try_get(8)?,
+                avg: row.try_get(9)?,
+                n_tup_ins: row.try_get(10)?,
+                n_tup_upd: row.try_get(11)?,
+                n_tup_del: row.try_get(12)?,
+                n_tup_hot_upd: row.try_get(13)?,
+                n_live_tup: row.try_get(14)?,
+                n_dead_tup: row.try_get(15)?,
+                n_mod_since_analyze: row.try_get(16)?,
+                n_ins_since_vacuum: row.try_get(17)?,
+                last_vacuum: row.try_get(18)?,
+                last_autovacuum: row.try_get(19)?,
+                last_analyze: row.try_get(20)?,
+                last_autoanalyze: row.try_get(21)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut seq_scan = MetricFamily::counter(
+        "pg_stat_user_tables_seq_scan",
+        "Number of sequential scans initiated on this table",
+    );
+    let mut seq_tup_read = MetricFamily::counter(
+        "pg_stat_user_tables_seq_tup_read",
+        "Number of live rows fetched by sequential scans",
+    );
+    let mut idx_scan = MetricFamily::counter(
+        "pg_stat_user_tables_idx_scan",
+        "Number of index scans initiated on this table",
+    );
+    let mut n_tup_ins = MetricFamily::counter(
+        "pg_stat_user_tables_n_tup_ins",
+        "Number of rows inserted",
+    );
+    let mut n_tup_upd = MetricFamily::counter(
+        "pg_stat_user_tables_n_tup_upd",
+        "Number of rows updated, including HOT updates",
+    );
+    let mut n_tup_del = MetricFamily::counter(
+        "pg_stat_user_tables_n_tup_del",
+        "Number of rows deleted",
+    );
+    let mut n_tup_hot_upd = MetricFamily::counter(
+        "pg_stat_user_tables_n_tup_hot_upd",
+        "Number of rows HOT updated, i.e. with no separate index update required",
+    );
+    let mut n_live_tup = MetricFamily::gauge(
+        "pg_stat_user_tables_n_live_tup",
+        "Estimated number of live rows",
+    );
+    let mut n_dead_tup = MetricFamily::gauge(
+        "pg_stat_user_tables_n_dead_tup",
+        "Estimated number of dead rows",
+    );
+    let mut n_mod_since_analyze = MetricFamily::gauge(
+        "pg_stat_user_tables_n_mod_since_analyze",
+        "Estimated number of rows modified since this table was last analyzed",
+    );
+    let mut n_ins_since_vacuum = MetricFamily::gauge(
+        "pg_stat_user_tables_n_ins_since_vacuum",
+        "Estimated number of rows inserted since this table was last vacuumed",
+    );
+    let mut last_vacuum = MetricFamily::gauge(
+        "pg_stat_user_tables_last_vacuum_timestamp_seconds",
+        "Last time at which this table was manually vacuumed, as a Unix timestamp",
+    )
+    .with_unit("seconds");
+    let mut last_autovacuum = MetricFamily::gauge(
+        "pg_stat_user_tables_last_autovacuum_timestamp_seconds",
+        "Last time at which this table was vacuumed by the autovacuum daemon, as a Unix timestamp",
+    )
+    .with_unit("seconds");
+    let mut last_analyze = MetricFamily::gauge(
+        "pg_stat_user_tables_last_analyze_timestamp_seconds",
+        "Last time at which this table was manually analyzed, as a Unix timestamp",
+    )
+    .with_unit("seconds");
+    let mut last_autoanalyze = MetricFamily::gauge(
+        "pg_stat_user_tables_last_autoanalyze_timestamp_seconds",
+        "Last time at which this table was analyzed by the autovacuum daemon, as a Unix timestamp",
+    )
+    .with_unit("seconds");
+    let mut vacuum_count = MetricFamily::counter(
+        "pg_stat_user_tables_vacuum_count",
+        "Number of times this table has been manually vacuumed",
+    );
+    let mut autovacuum_count = MetricFamily::counter(
+        "pg_stat_user_tables_autovacuum_count",
+        "Number of times this table has been vacuumed by the autovacuum daemon",
+    );
+    let mut analyze_count = MetricFamily::counter(
+        "pg_stat_user_tables_analyze_count",
+        "Number of times this table has been manually analyzed",
+    );
+    let mut autoanalyze_count = MetricFamily::counter(
+        "pg_stat_user_tables_autoanalyze_count",
+        "Number of times this table has been analyzed by the autovacuum daemon",
+    );
+    let mut avg = MetricFamily::gauge(
+        "pg_stat_user_tables_seq_tup_read_per_scan",
+        "Average number of rows read per sequential scan",
+    );
+    for i in &common_effectiveness {
+        let labels = [
+            ("schemaname", i.schemaname.as_str()),
+            ("relname", i.relname.as_str()),
+        ];
+        seq_scan.sample(&labels, i.seq_scan as f64);
+        seq_tup_read.sample(&labels, i.seq_tup_read as f64);
+        if let Some(value) = i.idx_scan {
+            idx_scan.sample(&labels, value as f64);
+        }
+        n_tup_ins.sample(&labels, i.n_tup_ins as f64);
+        n_tup_upd.sample(&labels, i.n_tup_upd as f64);
+        n_tup_del.sample(&labels, i.n_tup_del as f64);
+        n_tup_hot_upd.sample(&labels, i.n_tup_hot_upd as f64);
+        n_live_tup.sample(&labels, i.n_live_tup as f64);
+        n_dead_tup.sample(&labels, i.n_dead_tup as f64);
+        n_mod_since_analyze.sample(&labels, i.n_mod_since_analyze as f64);
+        if let Some(value) = i.n_ins_since_vacuum {
+            n_ins_since_vacuum.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.last_vacuum {
+            last_vacuum.sample(&labels, value);
+        }
+        if let Some(value) = i.last_autovacuum {
+            last_autovacuum.sample(&labels, value);
+        }
+        if let Some(value) = i.last_analyze {
+            last_analyze.sample(&labels, value);
+        }
+        if let Some(value) = i.last_autoanalyze {
+            last_autoanalyze.sample(&labels, value);
+        }
+        vacuum_count.sample(&labels, i.vacuum_count as f64);
+        autovacuum_count.sample(&labels, i.autovacuum_count as f64);
+        analyze_count.sample(&labels, i.analyze_count as f64);
+        autoanalyze_count.sample(&labels, i.autoanalyze_count as f64);
+        // Tables that were never sequentially scanned have no average.
+        if let Some(value) = i.avg {
+            avg.sample(&labels, value as f64);
+        }
+    }
+    Ok(vec![
+        seq_scan,
+        seq_tup_read,
+        idx_scan,
+        n_tup_ins,
+        n_tup_upd,
+        n_tup_del,
+        n_tup_hot_upd,
+        n_live_tup,
+        n_dead_tup,
+        n_mod_since_analyze,
+        n_ins_since_vacuum,
+        last_vacuum,
+        last_autovacuum,
+        last_analyze,
+        last_autoanalyze,
+        vacuum_count,
+        autovacuum_count,
+        analyze_count,
+        autoanalyze_count,
+        avg,
+    ])
+}
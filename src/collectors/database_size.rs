@@ -0,0 +1,43 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct DatabaseSize {
+    datname: String,
+    size: i64,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // pg_database_size fails on databases the exporter may not connect to.
+    let database_size_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                datname,
+                pg_database_size(oid) size
+            FROM
+                pg_database
+            WHERE
+                has_database_privilege(oid, 'CONNECT')")
+        .await?;
+    let database_size_rows = postgres_client
+        .query(&database_size_statement, &[])
+        .await?;
+    let database_size = database_size_rows
+        .iter()
+        .map(|row| {
+            Ok(DatabaseSize {
+                datname: row.try_get(0)?,
+                size: row.try_get(1)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut size_bytes = MetricFamily::gauge(
+        "pg_database_size_bytes",
+        "Disk space used by the database",
+    )
+    .with_unit("bytes");
+    for i in &database_size {
+        size_bytes.sample(&[("datname", i.datname.as_str())], i.size as f64);
+    }
+    Ok(vec![size_bytes])
+}
@@ -0,0 +1,59 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+use rust_decimal::prelude::*;
+
+#[derive(Debug)]
+struct HitMiss {
+    heap_read: Option<Decimal>,
+    heap_hit: Option<Decimal>,
+    ratio: Option<Decimal>,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let hit_miss_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                sum(heap_blks_read) as heap_read,
+                sum(heap_blks_hit) as heap_hit,
+                sum(heap_blks_hit) / nullif(sum(heap_blks_hit) + sum(heap_blks_read), 0) as ratio
+            FROM pg_statio_user_tables")
+        .await?;
+    let hit_miss_rows = postgres_client
+        .query(&hit_miss_statement, &[])
+        .await?;
+    let hit_miss = hit_miss_rows
+        .iter()
+        .map(|row| {
+            Ok(HitMiss {
+                heap_read: row.try_get(0)?,
+                heap_hit: row.try_get(1)?,
+                ratio: row.try_get(2)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut heap_read = MetricFamily::counter(
+        "pg_statio_user_tables_heap_blks_read",
+        "Number of disk blocks read from user tables",
+    );
+    let mut heap_hit = MetricFamily::counter(
+        "pg_statio_user_tables_heap_blks_hit",
+        "Number of buffer hits in user tables",
+    );
+    let mut ratio = MetricFamily::gauge(
+        "pg_statio_user_tables_heap_blks_hit_ratio",
+        "Ratio of buffer hits to all block accesses in user tables",
+    )
+    .with_unit("ratio");
+    for i in &hit_miss {
+        if let Some(value) = i.heap_read.and_then(|value| value.to_f64()) {
+            heap_read.sample(&[], value);
+        }
+        if let Some(value) = i.heap_hit.and_then(|value| value.to_f64()) {
+            heap_hit.sample(&[], value);
+        }
+        if let Some(value) = i.ratio.and_then(|value| value.to_f64()) {
+            ratio.sample(&[], value);
+        }
+    }
+    Ok(vec![heap_read, heap_hit, ratio])
+}
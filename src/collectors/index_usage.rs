@@ -0,0 +1,53 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct IndexUsage {
+    schemaname: String,
+    relname: String,
+    percent_of_times_index_used: Option<i64>,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let index_usage_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+              schemaname,
+              relname,
+              100 * idx_scan / (seq_scan + idx_scan) percent_of_times_index_used
+            FROM
+              pg_stat_user_tables
+            WHERE
+                seq_scan + idx_scan > 0
+            ORDER BY
+              n_live_tup DESC;",
+            )
+            .await?;
+    let index_usage_rows = postgres_client
+        .query(&index_usage_statement, &[])
+        .await?;
+    let index_usage = index_usage_rows
+        .iter()
+        .map(|row| {
+            Ok(IndexUsage {
+                schemaname: row.try_get(0)?,
+                relname: row.try_get(1)?,
+                percent_of_times_index_used: row.try_get(2)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut percent_of_times_index_used = MetricFamily::gauge(
+        "pg_stat_user_tables_index_usage_percent",
+        "Percentage of scans on this table that used an index",
+    );
+    for i in &index_usage {
+        let labels = [
+            ("schemaname", i.schemaname.as_str()),
+            ("relname", i.relname.as_str()),
+        ];
+        if let Some(value) = i.percent_of_times_index_used {
+            percent_of_times_index_used.sample(&labels, value as f64);
+        }
+    }
+    Ok(vec![percent_of_times_index_used])
+}
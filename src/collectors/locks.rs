@@ -0,0 +1,110 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct Locks {
+    datname: String,
+    mode: String,
+    locktype: String,
+    granted: bool,
+    count: i64,
+}
+
+#[derive(Debug)]
+struct LockWaits {
+    blocked_backends: i64,
+    longest_wait: f64,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // Locks held by the exporter's own backend are left out.
+    let locks_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                coalesce(a.datname, '') datname,
+                l.mode,
+                l.locktype,
+                l.granted,
+                count(*) count
+            FROM
+                pg_locks l
+                LEFT JOIN pg_stat_activity a ON a.pid = l.pid
+            WHERE
+                l.pid IS DISTINCT FROM pg_backend_pid()
+            GROUP BY 1, 2, 3, 4")
+        .await?;
+    let locks_rows = postgres_client
+        .query(&locks_statement, &[])
+        .await?;
+    let locks = locks_rows
+        .iter()
+        .map(|row| {
+            Ok(Locks {
+                datname: row.try_get(0)?,
+                mode: row.try_get(1)?,
+                locktype: row.try_get(2)?,
+                granted: row.try_get(3)?,
+                count: row.try_get(4)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    // pg_blocking_pids briefly locks the lock manager, so it is only called
+    // for backends actually waiting on a lock; OFFSET 0 keeps the planner
+    // from merging the two filters.
+    // pg_locks.waitstart appeared in PostgreSQL 14. Before that a backend
+    // waiting for a lock stays in the state it entered when the statement
+    // started, so state_change bounds the wait from above.
+    let longest_wait_column = if server_version >= 140000 {
+        "(SELECT coalesce(max(extract(epoch FROM clock_timestamp() - waitstart)), 0)
+                    FROM pg_locks WHERE NOT granted)::float8"
+    } else {
+        "(SELECT coalesce(max(extract(epoch FROM clock_timestamp() - state_change)), 0)
+                    FROM pg_stat_activity WHERE wait_event_type = 'Lock')::float8"
+    };
+    let lock_waits_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                (SELECT count(*) FROM
+                    (SELECT pid FROM pg_stat_activity WHERE wait_event_type = 'Lock' OFFSET 0) w
+                    WHERE cardinality(pg_blocking_pids(w.pid)) > 0) blocked_backends,
+                {} longest_wait",
+            longest_wait_column
+        ))
+        .await?;
+    let lock_waits_row = postgres_client
+        .query_one(&lock_waits_statement, &[])
+        .await?;
+    let lock_waits = LockWaits {
+        blocked_backends: lock_waits_row.try_get(0)?,
+        longest_wait: lock_waits_row.try_get(1)?,
+    };
+    let mut granted = MetricFamily::gauge("pg_locks_granted", "Number of locks held");
+    let mut waiting = MetricFamily::gauge("pg_locks_waiting", "Number of locks awaited");
+    for i in &locks {
+        let labels = [
+            ("datname", i.datname.as_str()),
+            ("mode", i.mode.as_str()),
+            ("locktype", i.locktype.as_str()),
+        ];
+        if i.granted {
+            granted.sample(&labels, i.count as f64);
+        } else {
+            waiting.sample(&labels, i.count as f64);
+        }
+    }
+    let mut blocked_backends = MetricFamily::gauge(
+        "pg_locks_blocked_backends",
+        "Number of backends blocked by another backend's lock",
+    );
+    blocked_backends.sample(&[], lock_waits.blocked_backends as f64);
+    let mut longest_wait = MetricFamily::gauge(
+        "pg_locks_longest_wait_seconds",
+        "Time the longest currently waiting lock request has been waiting",
+    )
+    .with_unit("seconds");
+    longest_wait.sample(&[], lock_waits.longest_wait);
+    Ok(vec![granted, waiting, blocked_backends, longest_wait])
+}
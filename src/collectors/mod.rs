@@ -0,0 +1,244 @@
+mod archiver;
+mod bgwriter;
+mod common_effectiveness;
+mod database_size;
+mod hit_miss;
+mod index_usage;
+mod locks;
+mod progress;
+mod replication;
+mod replication_slots;
+mod settings;
+mod stat_activity;
+mod stat_database;
+mod stat_io;
+mod statements;
+mod table_size;
+mod table_wraparound;
+mod user_indexes;
+mod wal;
+mod wraparound;
+
+use crate::discovery::{self, DatabaseFilter};
+use crate::exposition::{self, MetricFamily};
+use crate::pool::Client;
+use crate::target::Target;
+
+/// Collectors reading cluster-wide views, run once per scrape.
+pub const SERVER_COLLECTORS: &[&str] = &[
+    "stat_database",
+    "stat_activity",
+    "replication",
+    "replication_slots",
+    "bgwriter",
+    "locks",
+    "statements",
+    "database_size",
+    "wraparound",
+    "settings",
+    "wal",
+    "archiver",
+    "stat_io",
+    "progress",
+];
+
+/// Collectors reading per-database views, run against every discovered
+/// database when autodiscovery is enabled.
+pub const DATABASE_COLLECTORS: &[&str] = &[
+    "common_effectiveness",
+    "hit_miss",
+    "index_usage",
+    "table_size",
+    "user_indexes",
+    "table_wraparound",
+];
+
+/// Settings of individual collectors.
+#[derive(Debug, Clone)]
+pub struct CollectorOptions {
+    /// Number of statements exported by the `statements` collector.
+    pub statements_limit: i64,
+    /// Number of tables exported by the `table_wraparound` collector.
+    pub wraparound_table_limit: i64,
+}
+
+async fn collect(
+    name: &str,
+    postgres_client: &Client,
+    server_version: i32,
+    options: &CollectorOptions,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    match name {
+        "stat_database" => stat_database::collect(postgres_client, server_version).await,
+        "stat_activity" => stat_activity::collect(postgres_client, server_version).await,
+        "replication" => replication::collect(postgres_client, server_version).await,
+        "replication_slots" => replication_slots::collect(postgres_client, server_version).await,
+        "bgwriter" => bgwriter::collect(postgres_client, server_version).await,
+        "locks" => locks::collect(postgres_client, server_version).await,
+        "statements" => statements::collect(postgres_client, options.statements_limit).await,
+        "database_size" => database_size::collect(postgres_client).await,
+        "wraparound" => wraparound::collect(postgres_client).await,
+        "settings" => settings::collect(postgres_client).await,
+        "wal" => wal::collect(postgres_client, server_version).await,
+        "archiver" => archiver::collect(postgres_client).await,
+        "stat_io" => stat_io::collect(postgres_client, server_version).await,
+        "common_effectiveness" => {
+            common_effectiveness::collect(postgres_client, server_version).await
+        }
+        "hit_miss" => hit_miss::collect(postgres_client).await,
+        "index_usage" => index_usage::collect(postgres_client).await,
+        "table_size" => table_size::collect(postgres_client).await,
+        "user_indexes" => user_indexes::collect(postgres_client).await,
+        "table_wraparound" => {
+            table_wraparound::collect(postgres_client, options.wraparound_table_limit).await
+        }
+        "progress" => progress::collect(postgres_client, server_version).await,
+        _ => unreachable!("unknown collector {}", name),
+    }
+}
+
+/// `server_version_num` of the connected server, e.g. 150004.
+async fn server_version(postgres_client: &Client) -> Result<i32, tokio_postgres::Error> {
+    let server_version_statement = postgres_client
+        .prepare_cached("SELECT current_setting('server_version_num')::int")
+        .await?;
+    postgres_client
+        .query_one(&server_version_statement, &[])
+        .await?
+        .try_get(0)
+}
+
+struct Scrape {
+    server_version: i32,
+    options: CollectorOptions,
+    families: Vec<MetricFamily>,
+    collector_success: MetricFamily,
+    failed: bool,
+}
+
+impl Scrape {
+    async fn run(&mut self, name: &str, postgres_client: &Client, datname: Option<&str>) {
+        match collect(name, postgres_client, self.server_version, &self.options).await {
+            Ok(mut collected) => {
+                if let Some(datname) = datname {
+                    for family in &mut collected {
+                        family.add_label("datname", datname);
+                    }
+                }
+                self.families.extend(collected);
+                self.record(name, datname, true);
+            }
+            Err(e) => {
+                match datname {
+                    Some(datname) => eprintln!("collector {} failed on {}: {}", name, datname, e),
+                    None => eprintln!("collector {} failed: {}", name, e),
+                }
+                self.record(name, datname, false);
+            }
+        }
+    }
+
+    fn record(&mut self, name: &str, datname: Option<&str>, success: bool) {
+        let mut labels = vec![("collector", name)];
+        labels.extend(datname.map(|datname| ("datname", datname)));
+        self.collector_success
+            .sample(&labels, if success { 1.0 } else { 0.0 });
+        self.failed |= !success;
+    }
+
+    async fn run_database(&mut self, target: &Target, datname: &str) {
+        let postgres_client = match target.database_pool(datname) {
+            Ok(pool) => pool.get().await.map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+        match postgres_client {
+            Ok(postgres_client) => {
+                for name in DATABASE_COLLECTORS {
+                    self.run(name, &postgres_client, Some(datname)).await;
+                }
+            }
+            Err(e) => {
+                eprintln!("failed to connect to database {}: {}", datname, e);
+                for name in DATABASE_COLLECTORS {
+                    self.record(name, Some(datname), false);
+                }
+            }
+        }
+    }
+}
+
+/// Runs every collector against `target`. Failures never abort the scrape:
+/// they are logged and reported through `pg_up`,
+/// `pg_exporter_collector_success` and `pg_exporter_last_scrape_error`
+/// next to whatever could be collected. With `databases` set, the
+/// per-database collectors run on each matching database and their series
+/// are labelled with `datname`.
+pub async fn scrape(
+    target: &Target,
+    databases: Option<&DatabaseFilter>,
+    options: &CollectorOptions,
+) -> Vec<MetricFamily> {
+    let mut scrape = Scrape {
+        server_version: 0,
+        options: options.clone(),
+        families: Vec::new(),
+        collector_success: MetricFamily::gauge(
+            "pg_exporter_collector_success",
+            "Whether the collector succeeded during the last scrape",
+        ),
+        failed: false,
+    };
+    let mut up = MetricFamily::gauge("pg_up", "Whether the last scrape could connect to PostgreSQL");
+    let mut last_scrape_error = MetricFamily::gauge(
+        "pg_exporter_last_scrape_error",
+        "Whether the last scrape resulted in an error (1 for error, 0 for success)",
+    );
+    let postgres_client = match target.pool().get().await {
+        Ok(postgres_client) => server_version(&postgres_client)
+            .await
+            .map(|server_version| (postgres_client, server_version))
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match postgres_client {
+        Ok((postgres_client, server_version)) => {
+            up.sample(&[], 1.0);
+            scrape.server_version = server_version;
+            for name in SERVER_COLLECTORS {
+                scrape.run(name, &postgres_client, None).await;
+            }
+            match databases {
+                None => {
+                    for name in DATABASE_COLLECTORS {
+                        scrape.run(name, &postgres_client, None).await;
+                    }
+                }
+                Some(filter) => match discovery::list_databases(&postgres_client, filter).await {
+                    Ok(datnames) => {
+                        drop(postgres_client);
+                        for datname in &datnames {
+                            scrape.run_database(target, datname).await;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("failed to discover databases: {}", e);
+                        scrape.failed = true;
+                    }
+                },
+            }
+        }
+        Err(e) => {
+            eprintln!("failed to connect to PostgreSQL: {}", e);
+            up.sample(&[], 0.0);
+            for name in SERVER_COLLECTORS.iter().chain(DATABASE_COLLECTORS) {
+                scrape.record(name, None, false);
+            }
+        }
+    }
+    last_scrape_error.sample(&[], if scrape.failed { 1.0 } else { 0.0 });
+    let mut families = exposition::merge(scrape.families);
+    families.push(up);
+    families.push(scrape.collector_success);
+    families.push(last_scrape_error);
+    families
+}
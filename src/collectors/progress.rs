@@ -0,0 +1,139 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+/// A maintenance command in progress, from one of the
+/// `pg_stat_progress_*` views.
+#[derive(Debug)]
+struct Progress {
+    datname: String,
+    command: String,
+    pid: i32,
+    relid: String,
+    schemaname: String,
+    relname: String,
+    phase: String,
+    blocks_total: i64,
+    blocks_done: i64,
+    elapsed: Option<f64>,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let mut views = vec![
+        "SELECT datid, datname, pid, relid, 'VACUUM' command, phase,
+                    heap_blks_total blocks_total, heap_blks_scanned blocks_done
+                FROM pg_stat_progress_vacuum",
+    ];
+    if server_version >= 120000 {
+        views.push(
+            "SELECT datid, datname, pid, relid, command, phase,
+                    blocks_total, blocks_done
+                FROM pg_stat_progress_create_index",
+        );
+        views.push(
+            "SELECT datid, datname, pid, relid, command, phase,
+                    heap_blks_total, heap_blks_scanned
+                FROM pg_stat_progress_cluster",
+        );
+    }
+    if server_version >= 130000 {
+        views.push(
+            "SELECT datid, datname, pid, relid, 'ANALYZE', phase,
+                    sample_blks_total, sample_blks_scanned
+                FROM pg_stat_progress_analyze",
+        );
+    }
+    // The views cover the whole cluster, but relation names can only be
+    // resolved in the database the exporter is connected to; elsewhere only
+    // the relid label identifies the relation.
+    let progress_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                coalesce(p.datname, '') datname,
+                p.command,
+                p.pid,
+                p.relid::text,
+                coalesce(n.nspname, '') schemaname,
+                coalesce(c.relname, '') relname,
+                p.phase,
+                p.blocks_total,
+                p.blocks_done,
+                extract(epoch FROM clock_timestamp() - a.query_start)::float8 elapsed
+            FROM
+                ({}) p
+                LEFT JOIN pg_class c ON c.oid = p.relid
+                    AND p.datid = (SELECT oid FROM pg_database WHERE datname = current_database())
+                LEFT JOIN pg_namespace n ON n.oid = c.relnamespace
+                LEFT JOIN pg_stat_activity a ON a.pid = p.pid",
+            views.join("\n                UNION ALL\n                ")
+        ))
+        .await?;
+    let progress_rows = postgres_client
+        .query(&progress_statement, &[])
+        .await?;
+    let progress = progress_rows
+        .iter()
+        .map(|row| {
+            Ok(Progress {
+                datname: row.try_get(0)?,
+                command: row.try_get(1)?,
+                pid: row.try_get(2)?,
+                relid: row.try_get(3)?,
+                schemaname: row.try_get(4)?,
+                relname: row.try_get(5)?,
+                phase: row.try_get(6)?,
+                blocks_total: row.try_get(7)?,
+                blocks_done: row.try_get(8)?,
+                elapsed: row.try_get(9)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut phase = MetricFamily::gauge(
+        "pg_stat_progress_phase",
+        "Current processing phase of the maintenance command, always 1",
+    );
+    let mut blocks_total = MetricFamily::gauge(
+        "pg_stat_progress_blocks_total",
+        "Number of blocks the current phase of the maintenance command will process",
+    );
+    let mut blocks_done = MetricFamily::gauge(
+        "pg_stat_progress_blocks_done",
+        "Number of blocks the current phase of the maintenance command has processed",
+    );
+    let mut elapsed = MetricFamily::gauge(
+        "pg_stat_progress_elapsed_seconds",
+        "Time since the maintenance command started",
+    )
+    .with_unit("seconds");
+    for i in &progress {
+        let pid = i.pid.to_string();
+        let labels = [
+            ("datname", i.datname.as_str()),
+            ("command", i.command.as_str()),
+            ("pid", pid.as_str()),
+            ("relid", i.relid.as_str()),
+            ("schemaname", i.schemaname.as_str()),
+            ("relname", i.relname.as_str()),
+        ];
+        phase.sample(
+            &[
+                ("datname", i.datname.as_str()),
+                ("command", i.command.as_str()),
+                ("pid", pid.as_str()),
+                ("relid", i.relid.as_str()),
+                ("schemaname", i.schemaname.as_str()),
+                ("relname", i.relname.as_str()),
+                ("phase", i.phase.as_str()),
+            ],
+            1.0,
+        );
+        blocks_total.sample(&labels, i.blocks_total as f64);
+        blocks_done.sample(&labels, i.blocks_done as f64);
+        if let Some(value) = i.elapsed {
+            elapsed.sample(&labels, value);
+        }
+    }
+    Ok(vec![phase, blocks_total, blocks_done, elapsed])
+}
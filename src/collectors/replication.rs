@@ -0,0 +1,205 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+/// A standby connected to this server, from `pg_stat_replication`.
+#[derive(Debug)]
+struct StatReplication {
+    application_name: String,
+    client_addr: String,
+    sync_state: String,
+    write_lag: Option<f64>,
+    flush_lag: Option<f64>,
+    replay_lag: Option<f64>,
+    sent_lag_bytes: Option<f64>,
+    write_lag_bytes: Option<f64>,
+    flush_lag_bytes: Option<f64>,
+    replay_lag_bytes: Option<f64>,
+}
+
+/// Replay progress of this server when it is a standby.
+#[derive(Debug)]
+struct Recovery {
+    is_replica: bool,
+    lag_seconds: Option<f64>,
+    replay_lag_bytes: Option<f64>,
+    wal_receiver_streaming: i64,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // The *_lag columns and the wal/lsn function names are PostgreSQL 10+.
+    if server_version < 100000 {
+        return Ok(Vec::new());
+    }
+    let stat_replication_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                coalesce(application_name, '') application_name,
+                coalesce(host(client_addr), '') client_addr,
+                coalesce(sync_state, '') sync_state,
+                extract(epoch FROM write_lag)::float8 write_lag,
+                extract(epoch FROM flush_lag)::float8 flush_lag,
+                extract(epoch FROM replay_lag)::float8 replay_lag,
+                pg_wal_lsn_diff(current_lsn, sent_lsn)::float8 sent_lag_bytes,
+                pg_wal_lsn_diff(current_lsn, write_lsn)::float8 write_lag_bytes,
+                pg_wal_lsn_diff(current_lsn, flush_lsn)::float8 flush_lag_bytes,
+                pg_wal_lsn_diff(current_lsn, replay_lsn)::float8 replay_lag_bytes
+            FROM
+                pg_stat_replication,
+                LATERAL (SELECT CASE WHEN pg_is_in_recovery()
+                    THEN pg_last_wal_receive_lsn()
+                    ELSE pg_current_wal_lsn() END current_lsn) lsn")
+        .await?;
+    let stat_replication_rows = postgres_client
+        .query(&stat_replication_statement, &[])
+        .await?;
+    let stat_replication = stat_replication_rows
+        .iter()
+        .map(|row| {
+            Ok(StatReplication {
+                application_name: row.try_get(0)?,
+                client_addr: row.try_get(1)?,
+                sync_state: row.try_get(2)?,
+                write_lag: row.try_get(3)?,
+                flush_lag: row.try_get(4)?,
+                replay_lag: row.try_get(5)?,
+                sent_lag_bytes: row.try_get(6)?,
+                write_lag_bytes: row.try_get(7)?,
+                flush_lag_bytes: row.try_get(8)?,
+                replay_lag_bytes: row.try_get(9)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let recovery_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                pg_is_in_recovery() is_replica,
+                CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                    ELSE extract(epoch FROM now() - pg_last_xact_replay_timestamp())
+                END::float8 lag_seconds,
+                pg_wal_lsn_diff(pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn())::float8 replay_lag_bytes,
+                (SELECT count(*) FROM pg_stat_wal_receiver WHERE status = 'streaming') wal_receiver_streaming")
+        .await?;
+    let recovery_row = postgres_client
+        .query_one(&recovery_statement, &[])
+        .await?;
+    let recovery = Recovery {
+        is_replica: recovery_row.try_get(0)?,
+        lag_seconds: recovery_row.try_get(1)?,
+        replay_lag_bytes: recovery_row.try_get(2)?,
+        wal_receiver_streaming: recovery_row.try_get(3)?,
+    };
+    let mut sync_state = MetricFamily::gauge(
+        "pg_stat_replication_sync_state",
+        "Synchronous state of the standby, always 1",
+    );
+    let mut write_lag = MetricFamily::gauge(
+        "pg_stat_replication_write_lag_seconds",
+        "Time elapsed between flushing recent WAL locally and receiving notification that the standby has written it",
+    )
+    .with_unit("seconds");
+    let mut flush_lag = MetricFamily::gauge(
+        "pg_stat_replication_flush_lag_seconds",
+        "Time elapsed between flushing recent WAL locally and receiving notification that the standby has flushed it",
+    )
+    .with_unit("seconds");
+    let mut replay_lag = MetricFamily::gauge(
+        "pg_stat_replication_replay_lag_seconds",
+        "Time elapsed between flushing recent WAL locally and receiving notification that the standby has applied it",
+    )
+    .with_unit("seconds");
+    let mut sent_lag_bytes = MetricFamily::gauge(
+        "pg_stat_replication_sent_lag_bytes",
+        "WAL not yet sent to the standby",
+    )
+    .with_unit("bytes");
+    let mut write_lag_bytes = MetricFamily::gauge(
+        "pg_stat_replication_write_lag_bytes",
+        "WAL not yet written to disk by the standby",
+    )
+    .with_unit("bytes");
+    let mut flush_lag_bytes = MetricFamily::gauge(
+        "pg_stat_replication_flush_lag_bytes",
+        "WAL not yet flushed to durable storage by the standby",
+    )
+    .with_unit("bytes");
+    let mut replay_lag_bytes = MetricFamily::gauge(
+        "pg_stat_replication_replay_lag_bytes",
+        "WAL not yet replayed by the standby",
+    )
+    .with_unit("bytes");
+    for i in &stat_replication {
+        let labels = [
+            ("application_name", i.application_name.as_str()),
+            ("client_addr", i.client_addr.as_str()),
+        ];
+        sync_state.sample(
+            &[
+                ("application_name", i.application_name.as_str()),
+                ("client_addr", i.client_addr.as_str()),
+                ("sync_state", i.sync_state.as_str()),
+            ],
+            1.0,
+        );
+        // The *_lag columns are NULL once the standby has caught up.
+        write_lag.sample(&labels, i.write_lag.unwrap_or(0.0));
+        flush_lag.sample(&labels, i.flush_lag.unwrap_or(0.0));
+        replay_lag.sample(&labels, i.replay_lag.unwrap_or(0.0));
+        if let Some(value) = i.sent_lag_bytes {
+            sent_lag_bytes.sample(&labels, value);
+        }
+        if let Some(value) = i.write_lag_bytes {
+            write_lag_bytes.sample(&labels, value);
+        }
+        if let Some(value) = i.flush_lag_bytes {
+            flush_lag_bytes.sample(&labels, value);
+        }
+        if let Some(value) = i.replay_lag_bytes {
+            replay_lag_bytes.sample(&labels, value);
+        }
+    }
+    let mut is_replica = MetricFamily::gauge(
+        "pg_replication_is_replica",
+        "Whether the server is a standby in recovery",
+    );
+    is_replica.sample(&[], if recovery.is_replica { 1.0 } else { 0.0 });
+    let mut lag_seconds = MetricFamily::gauge(
+        "pg_replication_lag_seconds",
+        "Time since the last transaction replayed on this standby, 0 when all received WAL is replayed",
+    )
+    .with_unit("seconds");
+    let mut replication_replay_lag_bytes = MetricFamily::gauge(
+        "pg_replication_replay_lag_bytes",
+        "WAL received by this standby but not yet replayed",
+    )
+    .with_unit("bytes");
+    let mut wal_receiver_streaming = MetricFamily::gauge(
+        "pg_stat_wal_receiver_streaming",
+        "Whether the WAL receiver of this standby is streaming from its upstream",
+    );
+    if recovery.is_replica {
+        if let Some(value) = recovery.lag_seconds {
+            lag_seconds.sample(&[], value);
+        }
+        if let Some(value) = recovery.replay_lag_bytes {
+            replication_replay_lag_bytes.sample(&[], value);
+        }
+        wal_receiver_streaming.sample(&[], recovery.wal_receiver_streaming as f64);
+    }
+    Ok(vec![
+        sync_state,
+        write_lag,
+        flush_lag,
+        replay_lag,
+        sent_lag_bytes,
+        write_lag_bytes,
+        flush_lag_bytes,
+        replay_lag_bytes,
+        is_replica,
+        lag_seconds,
+        replication_replay_lag_bytes,
+        wal_receiver_streaming,
+    ])
+}
@@ -0,0 +1,227 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct ReplicationSlot {
+    slot_name: String,
+    slot_type: String,
+    plugin: String,
+    database: String,
+    active: bool,
+    // NULL for slots that have never reserved WAL.
+    retained_wal_bytes: Option<f64>,
+    // PostgreSQL 13+.
+    wal_status: Option<String>,
+    // PostgreSQL 13+, NULL when max_slot_wal_keep_size is -1.
+    safe_wal_size: Option<i64>,
+}
+
+/// Decoding statistics of a logical slot, PostgreSQL 14+.
+#[derive(Debug)]
+struct StatReplicationSlot {
+    slot_name: String,
+    spill_txns: i64,
+    spill_count: i64,
+    spill_bytes: i64,
+    stream_txns: i64,
+    stream_count: i64,
+    stream_bytes: i64,
+    total_txns: i64,
+    total_bytes: i64,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // pg_wal_lsn_diff and the *_lsn functions are PostgreSQL 10+.
+    if server_version < 100000 {
+        return Ok(Vec::new());
+    }
+    let wal_status_columns = if server_version >= 130000 {
+        "wal_status,
+                safe_wal_size"
+    } else {
+        "NULL::text,
+                NULL::bigint"
+    };
+    let replication_slots_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                slot_name,
+                slot_type,
+                coalesce(plugin, '') plugin,
+                coalesce(database, '') database,
+                active,
+                pg_wal_lsn_diff(current_lsn, restart_lsn)::float8 retained_wal_bytes,
+                {}
+            FROM
+                pg_replication_slots,
+                LATERAL (SELECT CASE WHEN pg_is_in_recovery()
+                    THEN pg_last_wal_receive_lsn()
+                    ELSE pg_current_wal_lsn() END current_lsn) lsn",
+            wal_status_columns
+        ))
+        .await?;
+    let replication_slots_rows = postgres_client
+        .query(&replication_slots_statement, &[])
+        .await?;
+    let replication_slots = replication_slots_rows
+        .iter()
+        .map(|row| {
+            Ok(ReplicationSlot {
+                slot_name: row.try_get(0)?,
+                slot_type: row.try_get(1)?,
+                plugin: row.try_get(2)?,
+                database: row.try_get(3)?,
+                active: row.try_get(4)?,
+                retained_wal_bytes: row.try_get(5)?,
+                wal_status: row.try_get(6)?,
+                safe_wal_size: row.try_get(7)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let stat_replication_slots = if server_version >= 140000 {
+        let stat_replication_slots_statement = postgres_client
+            .prepare_cached(
+                "SELECT
+                    slot_name,
+                    spill_txns,
+                    spill_count,
+                    spill_bytes,
+                    stream_txns,
+                    stream_count,
+                    stream_bytes,
+                    total_txns,
+                    total_bytes
+                FROM
+                    pg_stat_replication_slots")
+            .await?;
+        let stat_replication_slots_rows = postgres_client
+            .query(&stat_replication_slots_statement, &[])
+            .await?;
+        stat_replication_slots_rows
+            .iter()
+            .map(|row| {
+                Ok(StatReplicationSlot {
+                    slot_name: row.try_get(0)?,
+                    spill_txns: row.try_get(1)?,
+                    spill_count: row.try_get(2)?,
+                    spill_bytes: row.try_get(3)?,
+                    stream_txns: row.try_get(4)?,
+                    stream_count: row.try_get(5)?,
+                    stream_bytes: row.try_get(6)?,
+                    total_txns: row.try_get(7)?,
+                    total_bytes: row.try_get(8)?,
+                })
+            })
+            .collect::<Result<Vec<_>, tokio_postgres::Error>>()?
+    } else {
+        Vec::new()
+    };
+    let mut active = MetricFamily::gauge(
+        "pg_replication_slots_active",
+        "Whether a consumer is currently connected to the replication slot",
+    );
+    let mut retained_wal_bytes = MetricFamily::gauge(
+        "pg_replication_slots_retained_wal_bytes",
+        "WAL kept on disk for the replication slot since its restart_lsn",
+    )
+    .with_unit("bytes");
+    let mut wal_status = MetricFamily::gauge(
+        "pg_replication_slots_wal_status",
+        "Availability of the WAL claimed by the replication slot, always 1",
+    );
+    let mut safe_wal_size = MetricFamily::gauge(
+        "pg_replication_slots_safe_wal_size_bytes",
+        "WAL that can be written before the replication slot is in danger of getting lost",
+    )
+    .with_unit("bytes");
+    for i in &replication_slots {
+        let labels = [("slot_name", i.slot_name.as_str())];
+        active.sample(
+            &[
+                ("slot_name", i.slot_name.as_str()),
+                ("slot_type", i.slot_type.as_str()),
+                ("plugin", i.plugin.as_str()),
+                ("database", i.database.as_str()),
+            ],
+            if i.active { 1.0 } else { 0.0 },
+        );
+        if let Some(value) = i.retained_wal_bytes {
+            retained_wal_bytes.sample(&labels, value);
+        }
+        if let Some(value) = &i.wal_status {
+            wal_status.sample(
+                &[
+                    ("slot_name", i.slot_name.as_str()),
+                    ("wal_status", value.as_str()),
+                ],
+                1.0,
+            );
+        }
+        if let Some(value) = i.safe_wal_size {
+            safe_wal_size.sample(&labels, value as f64);
+        }
+    }
+    let mut spill_txns = MetricFamily::counter(
+        "pg_stat_replication_slots_spill_txns",
+        "Number of transactions spilled to disk once logical decoding memory was exceeded",
+    );
+    let mut spill_count = MetricFamily::counter(
+        "pg_stat_replication_slots_spill_count",
+        "Number of times transactions were spilled to disk while decoding changes",
+    );
+    let mut spill_bytes = MetricFamily::counter(
+        "pg_stat_replication_slots_spill_bytes",
+        "Amount of decoded transaction data spilled to disk",
+    )
+    .with_unit("bytes");
+    let mut stream_txns = MetricFamily::counter(
+        "pg_stat_replication_slots_stream_txns",
+        "Number of in-progress transactions streamed to the decoding output plugin",
+    );
+    let mut stream_count = MetricFamily::counter(
+        "pg_stat_replication_slots_stream_count",
+        "Number of times in-progress transactions were streamed to the decoding output plugin",
+    );
+    let mut stream_bytes = MetricFamily::counter(
+        "pg_stat_replication_slots_stream_bytes",
+        "Amount of transaction data decoded for streaming in-progress transactions",
+    )
+    .with_unit("bytes");
+    let mut total_txns = MetricFamily::counter(
+        "pg_stat_replication_slots_total_txns",
+        "Number of decoded transactions sent to the decoding output plugin",
+    );
+    let mut total_bytes = MetricFamily::counter(
+        "pg_stat_replication_slots_total_bytes",
+        "Amount of transaction data decoded for sending transactions to the decoding output plugin",
+    )
+    .with_unit("bytes");
+    for i in &stat_replication_slots {
+        let labels = [("slot_name", i.slot_name.as_str())];
+        spill_txns.sample(&labels, i.spill_txns as f64);
+        spill_count.sample(&labels, i.spill_count as f64);
+        spill_bytes.sample(&labels, i.spill_bytes as f64);
+        stream_txns.sample(&labels, i.stream_txns as f64);
+        stream_count.sample(&labels, i.stream_count as f64);
+        stream_bytes.sample(&labels, i.stream_bytes as f64);
+        total_txns.sample(&labels, i.total_txns as f64);
+        total_bytes.sample(&labels, i.total_bytes as f64);
+    }
+    Ok(vec![
+        active,
+        retained_wal_bytes,
+        wal_status,
+        safe_wal_size,
+        spill_txns,
+        spill_count,
+        spill_bytes,
+        stream_txns,
+        stream_count,
+        stream_bytes,
+        total_txns,
+        total_bytes,
+    ])
+}
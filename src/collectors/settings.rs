@@ -0,0 +1,171 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct Setting {
+    name: String,
+    setting: String,
+    unit: String,
+    vartype: String,
+    short_desc: String,
+    pending_restart: bool,
+}
+
+/// Scale of a `pg_settings.unit` such as "8kB" or "ms" relative to its
+/// base unit, with the base unit's name.
+fn unit_scale(unit: &str) -> Option<(f64, &'static str)> {
+    let digits = unit
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(unit.len());
+    let count = if digits == 0 {
+        1.0
+    } else {
+        unit[..digits].parse::<f64>().ok()?
+    };
+    let (scale, base) = match &unit[digits..] {
+        "B" => (1.0, "bytes"),
+        "kB" => (1024.0, "bytes"),
+        "MB" => (1024.0 * 1024.0, "bytes"),
+        "GB" => (1024.0 * 1024.0 * 1024.0, "bytes"),
+        "TB" => (1024.0 * 1024.0 * 1024.0 * 1024.0, "bytes"),
+        "us" => (0.000001, "seconds"),
+        "ms" => (0.001, "seconds"),
+        "s" => (1.0, "seconds"),
+        "min" => (60.0, "seconds"),
+        "h" => (3600.0, "seconds"),
+        "d" => (86400.0, "seconds"),
+        _ => return None,
+    };
+    Some((count * scale, base))
+}
+
+/// Converts a setting to its base unit. Negative values such as -1 mean
+/// "disabled" and are kept as is.
+fn scale_value(value: f64, scale: f64) -> f64 {
+    if value < 0.0 {
+        value
+    } else {
+        value * scale
+    }
+}
+
+/// Metric name for a setting; custom settings such as
+/// `pg_stat_statements.max` contain dots.
+fn metric_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("pg_settings_{}", name)
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let settings_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                name,
+                setting,
+                coalesce(unit, '') unit,
+                vartype,
+                short_desc,
+                pending_restart
+            FROM
+                pg_settings
+            WHERE
+                vartype IN ('bool', 'integer', 'real')")
+        .await?;
+    let settings_rows = postgres_client
+        .query(&settings_statement, &[])
+        .await?;
+    let settings = settings_rows
+        .iter()
+        .map(|row| {
+            Ok(Setting {
+                name: row.try_get(0)?,
+                setting: row.try_get(1)?,
+                unit: row.try_get(2)?,
+                vartype: row.try_get(3)?,
+                short_desc: row.try_get(4)?,
+                pending_restart: row.try_get(5)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut families = Vec::new();
+    let mut pending_restart = false;
+    for i in &settings {
+        pending_restart |= i.pending_restart;
+        let value = if i.vartype == "bool" {
+            if i.setting == "on" {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            match i.setting.parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => continue,
+            }
+        };
+        let family = match unit_scale(&i.unit) {
+            Some((scale, base)) => {
+                let mut family = MetricFamily::gauge(
+                    format!("{}_{}", metric_name(&i.name), base),
+                    i.short_desc.as_str(),
+                )
+                .with_unit(base);
+                family.sample(&[], scale_value(value, scale));
+                family
+            }
+            None => {
+                let mut family = MetricFamily::gauge(metric_name(&i.name), i.short_desc.as_str());
+                family.sample(&[], value);
+                family
+            }
+        };
+        families.push(family);
+    }
+    let mut pending_restart_family = MetricFamily::gauge(
+        "pg_settings_pending_restart",
+        "Whether a setting has been changed in the configuration file but needs a restart to apply",
+    );
+    pending_restart_family.sample(&[], if pending_restart { 1.0 } else { 0.0 });
+    families.push(pending_restart_family);
+    Ok(families)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_scale_of_pg_settings_units() {
+        assert_eq!(unit_scale("8kB"), Some((8192.0, "bytes")));
+        assert_eq!(unit_scale("16MB"), Some((16.0 * 1024.0 * 1024.0, "bytes")));
+        assert_eq!(unit_scale("B"), Some((1.0, "bytes")));
+        assert_eq!(unit_scale("us"), Some((0.000001, "seconds")));
+        assert_eq!(unit_scale("ms"), Some((0.001, "seconds")));
+        assert_eq!(unit_scale("s"), Some((1.0, "seconds")));
+        assert_eq!(unit_scale("min"), Some((60.0, "seconds")));
+        assert_eq!(unit_scale("d"), Some((86400.0, "seconds")));
+        assert_eq!(unit_scale(""), None);
+        assert_eq!(unit_scale("8"), None);
+        assert_eq!(unit_scale("blocks"), None);
+    }
+
+    #[test]
+    fn scale_value_keeps_disabled_values() {
+        assert_eq!(scale_value(128.0, 8192.0), 1048576.0);
+        assert_eq!(scale_value(200.0, 0.001), 0.2);
+        assert_eq!(scale_value(0.0, 60.0), 0.0);
+        assert_eq!(scale_value(-1.0, 0.001), -1.0);
+    }
+
+    #[test]
+    fn metric_name_replaces_dots() {
+        assert_eq!(metric_name("shared_buffers"), "pg_settings_shared_buffers");
+        assert_eq!(
+            metric_name("pg_stat_statements.max"),
+            "pg_settings_pg_stat_statements_max"
+        );
+    }
+}
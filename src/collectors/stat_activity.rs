@@ -0,0 +1,131 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct StatActivity {
+    datname: String,
+    usename: String,
+    application_name: String,
+    backend_type: String,
+    state: String,
+    count: i64,
+    oldest_xact_age: Option<f64>,
+    oldest_query_age: Option<f64>,
+}
+
+#[derive(Debug)]
+struct ConnectionLimits {
+    max_connections: i32,
+    superuser_reserved_connections: i32,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // backend_type appeared in PostgreSQL 10; before that only client
+    // backends were listed.
+    let backend_type_column = if server_version >= 100000 {
+        "coalesce(backend_type, '')"
+    } else {
+        "'client backend'"
+    };
+    let stat_activity_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                coalesce(datname, '') datname,
+                coalesce(usename, '') usename,
+                coalesce(application_name, '') application_name,
+                {} backend_type,
+                state,
+                count(*) count,
+                max(extract(epoch FROM clock_timestamp() - xact_start))::float8 oldest_xact_age,
+                max(extract(epoch FROM clock_timestamp() - query_start))
+                    FILTER (WHERE state = 'active')::float8 oldest_query_age
+            FROM
+                pg_stat_activity
+            WHERE
+                state IS NOT NULL
+            GROUP BY 1, 2, 3, 4, 5",
+            backend_type_column
+        ))
+        .await?;
+    let stat_activity_rows = postgres_client
+        .query(&stat_activity_statement, &[])
+        .await?;
+    let stat_activity = stat_activity_rows
+        .iter()
+        .map(|row| {
+            Ok(StatActivity {
+                datname: row.try_get(0)?,
+                usename: row.try_get(1)?,
+                application_name: row.try_get(2)?,
+                backend_type: row.try_get(3)?,
+                state: row.try_get(4)?,
+                count: row.try_get(5)?,
+                oldest_xact_age: row.try_get(6)?,
+                oldest_query_age: row.try_get(7)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let connection_limits_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                current_setting('max_connections')::int max_connections,
+                current_setting('superuser_reserved_connections')::int superuser_reserved_connections")
+        .await?;
+    let connection_limits_row = postgres_client
+        .query_one(&connection_limits_statement, &[])
+        .await?;
+    let connection_limits = ConnectionLimits {
+        max_connections: connection_limits_row.try_get(0)?,
+        superuser_reserved_connections: connection_limits_row.try_get(1)?,
+    };
+    let mut count = MetricFamily::gauge(
+        "pg_stat_activity_count",
+        "Number of backends in this state",
+    );
+    let mut oldest_xact_age = MetricFamily::gauge(
+        "pg_stat_activity_oldest_xact_age_seconds",
+        "Age of the oldest transaction among backends in this state",
+    )
+    .with_unit("seconds");
+    let mut oldest_query_age = MetricFamily::gauge(
+        "pg_stat_activity_oldest_query_age_seconds",
+        "Age of the oldest running query among active backends",
+    )
+    .with_unit("seconds");
+    for i in &stat_activity {
+        let labels = [
+            ("datname", i.datname.as_str()),
+            ("usename", i.usename.as_str()),
+            ("application_name", i.application_name.as_str()),
+            ("backend_type", i.backend_type.as_str()),
+            ("state", i.state.as_str()),
+        ];
+        count.sample(&labels, i.count as f64);
+        if let Some(value) = i.oldest_xact_age {
+            oldest_xact_age.sample(&labels, value);
+        }
+        if let Some(value) = i.oldest_query_age {
+            oldest_query_age.sample(&labels, value);
+        }
+    }
+    let mut max_connections = MetricFamily::gauge(
+        "pg_max_connections",
+        "Maximum number of concurrent connections to the server",
+    );
+    max_connections.sample(&[], connection_limits.max_connections as f64);
+    let mut superuser_reserved_connections = MetricFamily::gauge(
+        "pg_superuser_reserved_connections",
+        "Number of connection slots reserved for superusers",
+    );
+    superuser_reserved_connections.sample(&[], connection_limits.superuser_reserved_connections as f64);
+    Ok(vec![
+        count,
+        oldest_xact_age,
+        oldest_query_age,
+        max_connections,
+        superuser_reserved_connections,
+    ])
+}
@@ -0,0 +1,296 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct StatDatabase {
+    datname: String,
+    numbackends: i32,
+    xact_commit: i64,
+    xact_rollback: i64,
+    blks_read: i64,
+    blks_hit: i64,
+    tup_returned: i64,
+    tup_fetched: i64,
+    tup_inserted: i64,
+    tup_updated: i64,
+    tup_deleted: i64,
+    conflicts: i64,
+    temp_files: i64,
+    temp_bytes: i64,
+    deadlocks: i64,
+    blk_read_time: f64,
+    blk_write_time: f64,
+    // PostgreSQL 12+, NULL when data checksums are disabled.
+    checksum_failures: Option<i64>,
+    // PostgreSQL 14+.
+    session_time: Option<f64>,
+    active_time: Option<f64>,
+    idle_in_transaction_time: Option<f64>,
+    sessions: Option<i64>,
+    sessions_abandoned: Option<i64>,
+    sessions_fatal: Option<i64>,
+    sessions_killed: Option<i64>,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let checksum_columns = if server_version >= 120000 {
+        "checksum_failures"
+    } else {
+        "NULL::bigint"
+    };
+    let session_columns = if server_version >= 140000 {
+        "session_time,
+                active_time,
+                idle_in_transaction_time,
+                sessions,
+                sessions_abandoned,
+                sessions_fatal,
+                sessions_killed"
+    } else {
+        "NULL::float8,
+                NULL::float8,
+                NULL::float8,
+                NULL::bigint,
+                NULL::bigint,
+                NULL::bigint,
+                NULL::bigint"
+    };
+    let stat_database_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                datname,
+                numbackends,
+                xact_commit,
+                xact_rollback,
+                blks_read,
+                blks_hit,
+                tup_returned,
+                tup_fetched,
+                tup_inserted,
+                tup_updated,
+                tup_deleted,
+                conflicts,
+                temp_files,
+                temp_bytes,
+                deadlocks,
+                blk_read_time,
+                blk_write_time,
+                {},
+                {}
+            FROM
+                pg_stat_database
+            WHERE
+                datname IS NOT NULL",
+            checksum_columns, session_columns
+        ))
+        .await?;
+    let stat_database_rows = postgres_client
+        .query(&stat_database_statement, &[])
+        .await?;
+    let stat_database = stat_database_rows
+        .iter()
+        .map(|row| {
+            Ok(StatDatabase {
+                datname: row.try_get(0)?,
+                numbackends: row.try_get(1)?,
+                xact_commit: row.try_get(2)?,
+                xact_rollback: row.try_get(3)?,
+                blks_read: row.try_get(4)?,
+                blks_hit: row.try_get(5)?,
+                tup_returned: row.try_get(6)?,
+                tup_fetched: row.try_get(7)?,
+                tup_inserted: row.try_get(8)?,
+                tup_updated: row.try_get(9)?,
+                tup_deleted: row.try_get(10)?,
+                conflicts: row.try_get(11)?,
+                temp_files: row.try_get(12)?,
+                temp_bytes: row.try_get(13)?,
+                deadlocks: row.try_get(14)?,
+                blk_read_time: row.try_get(15)?,
+                blk_write_time: row.try_get(16)?,
+                checksum_failures: row.try_get(17)?,
+                session_time: row.try_get(18)?,
+                active_time: row.try_get(19)?,
+                idle_in_transaction_time: row.try_get(20)?,
+                sessions: row.try_get(21)?,
+                sessions_abandoned: row.try_get(22)?,
+                sessions_fatal: row.try_get(23)?,
+                sessions_killed: row.try_get(24)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut numbackends = MetricFamily::gauge(
+        "pg_stat_database_numbackends",
+        "Number of backends currently connected to this database",
+    );
+    let mut xact_commit = MetricFamily::counter(
+        "pg_stat_database_xact_commit",
+        "Number of transactions in this database that have been committed",
+    );
+    let mut xact_rollback = MetricFamily::counter(
+        "pg_stat_database_xact_rollback",
+        "Number of transactions in this database that have been rolled back",
+    );
+    let mut blks_read = MetricFamily::counter(
+        "pg_stat_database_blks_read",
+        "Number of disk blocks read in this database",
+    );
+    let mut blks_hit = MetricFamily::counter(
+        "pg_stat_database_blks_hit",
+        "Number of times disk blocks were found already in the buffer cache",
+    );
+    let mut tup_returned = MetricFamily::counter(
+        "pg_stat_database_tup_returned",
+        "Number of live rows fetched by sequential scans and index entries returned by index scans",
+    );
+    let mut tup_fetched = MetricFamily::counter(
+        "pg_stat_database_tup_fetched",
+        "Number of live rows fetched by index scans",
+    );
+    let mut tup_inserted = MetricFamily::counter(
+        "pg_stat_database_tup_inserted",
+        "Number of rows inserted by queries in this database",
+    );
+    let mut tup_updated = MetricFamily::counter(
+        "pg_stat_database_tup_updated",
+        "Number of rows updated by queries in this database",
+    );
+    let mut tup_deleted = MetricFamily::counter(
+        "pg_stat_database_tup_deleted",
+        "Number of rows deleted by queries in this database",
+    );
+    let mut conflicts = MetricFamily::counter(
+        "pg_stat_database_conflicts",
+        "Number of queries canceled due to conflicts with recovery in this database",
+    );
+    let mut temp_files = MetricFamily::counter(
+        "pg_stat_database_temp_files",
+        "Number of temporary files created by queries in this database",
+    );
+    let mut temp_bytes = MetricFamily::counter(
+        "pg_stat_database_temp_bytes",
+        "Total amount of data written to temporary files by queries in this database",
+    )
+    .with_unit("bytes");
+    let mut deadlocks = MetricFamily::counter(
+        "pg_stat_database_deadlocks",
+        "Number of deadlocks detected in this database",
+    );
+    let mut blk_read_time = MetricFamily::counter(
+        "pg_stat_database_blk_read_time_seconds",
+        "Time spent reading data file blocks by backends in this database",
+    )
+    .with_unit("seconds");
+    let mut blk_write_time = MetricFamily::counter(
+        "pg_stat_database_blk_write_time_seconds",
+        "Time spent writing data file blocks by backends in this database",
+    )
+    .with_unit("seconds");
+    let mut checksum_failures = MetricFamily::counter(
+        "pg_stat_database_checksum_failures",
+        "Number of data page checksum failures detected in this database",
+    );
+    let mut session_time = MetricFamily::counter(
+        "pg_stat_database_session_time_seconds",
+        "Time spent by database sessions in this database",
+    )
+    .with_unit("seconds");
+    let mut active_time = MetricFamily::counter(
+        "pg_stat_database_active_time_seconds",
+        "Time spent executing SQL statements in this database",
+    )
+    .with_unit("seconds");
+    let mut idle_in_transaction_time = MetricFamily::counter(
+        "pg_stat_database_idle_in_transaction_time_seconds",
+        "Time spent idling while in a transaction in this database",
+    )
+    .with_unit("seconds");
+    let mut sessions = MetricFamily::counter(
+        "pg_stat_database_sessions",
+        "Total number of sessions established to this database",
+    );
+    let mut sessions_abandoned = MetricFamily::counter(
+        "pg_stat_database_sessions_abandoned",
+        "Number of sessions to this database that were terminated because connection to the client was lost",
+    );
+    let mut sessions_fatal = MetricFamily::counter(
+        "pg_stat_database_sessions_fatal",
+        "Number of sessions to this database that were terminated by fatal errors",
+    );
+    let mut sessions_killed = MetricFamily::counter(
+        "pg_stat_database_sessions_killed",
+        "Number of sessions to this database that were terminated by operator intervention",
+    );
+    for i in &stat_database {
+        let labels = [("datname", i.datname.as_str())];
+        numbackends.sample(&labels, i.numbackends as f64);
+        xact_commit.sample(&labels, i.xact_commit as f64);
+        xact_rollback.sample(&labels, i.xact_rollback as f64);
+        blks_read.sample(&labels, i.blks_read as f64);
+        blks_hit.sample(&labels, i.blks_hit as f64);
+        tup_returned.sample(&labels, i.tup_returned as f64);
+        tup_fetched.sample(&labels, i.tup_fetched as f64);
+        tup_inserted.sample(&labels, i.tup_inserted as f64);
+        tup_updated.sample(&labels, i.tup_updated as f64);
+        tup_deleted.sample(&labels, i.tup_deleted as f64);
+        conflicts.sample(&labels, i.conflicts as f64);
+        temp_files.sample(&labels, i.temp_files as f64);
+        temp_bytes.sample(&labels, i.temp_bytes as f64);
+        deadlocks.sample(&labels, i.deadlocks as f64);
+        blk_read_time.sample(&labels, i.blk_read_time / 1000.0);
+        blk_write_time.sample(&labels, i.blk_write_time / 1000.0);
+        if let Some(value) = i.checksum_failures {
+            checksum_failures.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.session_time {
+            session_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.active_time {
+            active_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.idle_in_transaction_time {
+            idle_in_transaction_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.sessions {
+            sessions.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.sessions_abandoned {
+            sessions_abandoned.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.sessions_fatal {
+            sessions_fatal.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.sessions_killed {
+            sessions_killed.sample(&labels, value as f64);
+        }
+    }
+    Ok(vec![
+        numbackends,
+        xact_commit,
+        xact_rollback,
+        blks_read,
+        blks_hit,
+        tup_returned,
+        tup_fetched,
+        tup_inserted,
+        tup_updated,
+        tup_deleted,
+        conflicts,
+        temp_files,
+        temp_bytes,
+        deadlocks,
+        blk_read_time,
+        blk_write_time,
+        checksum_failures,
+        session_time,
+        active_time,
+        idle_in_transaction_time,
+        sessions,
+        sessions_abandoned,
+        sessions_fatal,
+        sessions_killed,
+    ])
+}
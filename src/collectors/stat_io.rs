@@ -0,0 +1,191 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+/// A row of `pg_stat_io`. Operations that do not apply to a combination of
+/// backend type, object and context are NULL.
+#[derive(Debug)]
+struct StatIo {
+    backend_type: String,
+    object: String,
+    context: String,
+    reads: Option<i64>,
+    read_time: Option<f64>,
+    writes: Option<i64>,
+    write_time: Option<f64>,
+    writebacks: Option<i64>,
+    writeback_time: Option<f64>,
+    extends: Option<i64>,
+    extend_time: Option<f64>,
+    hits: Option<i64>,
+    evictions: Option<i64>,
+    reuses: Option<i64>,
+    fsyncs: Option<i64>,
+    fsync_time: Option<f64>,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // pg_stat_io appeared in PostgreSQL 16.
+    if server_version < 160000 {
+        return Ok(Vec::new());
+    }
+    let stat_io_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                backend_type,
+                object,
+                context,
+                reads,
+                read_time,
+                writes,
+                write_time,
+                writebacks,
+                writeback_time,
+                extends,
+                extend_time,
+                hits,
+                evictions,
+                reuses,
+                fsyncs,
+                fsync_time
+            FROM
+                pg_stat_io")
+        .await?;
+    let stat_io_rows = postgres_client
+        .query(&stat_io_statement, &[])
+        .await?;
+    let stat_io = stat_io_rows
+        .iter()
+        .map(|row| {
+            Ok(StatIo {
+                backend_type: row.try_get(0)?,
+                object: row.try_get(1)?,
+                context: row.try_get(2)?,
+                reads: row.try_get(3)?,
+                read_time: row.try_get(4)?,
+                writes: row.try_get(5)?,
+                write_time: row.try_get(6)?,
+                writebacks: row.try_get(7)?,
+                writeback_time: row.try_get(8)?,
+                extends: row.try_get(9)?,
+                extend_time: row.try_get(10)?,
+                hits: row.try_get(11)?,
+                evictions: row.try_get(12)?,
+                reuses: row.try_get(13)?,
+                fsyncs: row.try_get(14)?,
+                fsync_time: row.try_get(15)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut reads = MetricFamily::counter("pg_stat_io_reads", "Number of read operations");
+    let mut read_time = MetricFamily::counter(
+        "pg_stat_io_read_time_seconds",
+        "Time spent in read operations, when track_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    let mut writes = MetricFamily::counter("pg_stat_io_writes", "Number of write operations");
+    let mut write_time = MetricFamily::counter(
+        "pg_stat_io_write_time_seconds",
+        "Time spent in write operations, when track_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    let mut writebacks = MetricFamily::counter(
+        "pg_stat_io_writebacks",
+        "Number of units requested to be written back to permanent storage",
+    );
+    let mut writeback_time = MetricFamily::counter(
+        "pg_stat_io_writeback_time_seconds",
+        "Time spent in writeback operations, when track_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    let mut extends = MetricFamily::counter(
+        "pg_stat_io_extends",
+        "Number of relation extend operations",
+    );
+    let mut extend_time = MetricFamily::counter(
+        "pg_stat_io_extend_time_seconds",
+        "Time spent in extend operations, when track_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    let mut hits = MetricFamily::counter(
+        "pg_stat_io_hits",
+        "Number of times a desired block was found in a shared buffer",
+    );
+    let mut evictions = MetricFamily::counter(
+        "pg_stat_io_evictions",
+        "Number of times a block has been written out from a buffer to make it available for another use",
+    );
+    let mut reuses = MetricFamily::counter(
+        "pg_stat_io_reuses",
+        "Number of times an existing buffer in a ring buffer was reused",
+    );
+    let mut fsyncs = MetricFamily::counter("pg_stat_io_fsyncs", "Number of fsync calls");
+    let mut fsync_time = MetricFamily::counter(
+        "pg_stat_io_fsync_time_seconds",
+        "Time spent in fsync operations, when track_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    for i in &stat_io {
+        let labels = [
+            ("backend_type", i.backend_type.as_str()),
+            ("object", i.object.as_str()),
+            ("context", i.context.as_str()),
+        ];
+        if let Some(value) = i.reads {
+            reads.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.read_time {
+            read_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.writes {
+            writes.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.write_time {
+            write_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.writebacks {
+            writebacks.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.writeback_time {
+            writeback_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.extends {
+            extends.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.extend_time {
+            extend_time.sample(&labels, value / 1000.0);
+        }
+        if let Some(value) = i.hits {
+            hits.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.evictions {
+            evictions.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.reuses {
+            reuses.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.fsyncs {
+            fsyncs.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.fsync_time {
+            fsync_time.sample(&labels, value / 1000.0);
+        }
+    }
+    Ok(vec![
+        reads,
+        read_time,
+        writes,
+        write_time,
+        writebacks,
+        writeback_time,
+        extends,
+        extend_time,
+        hits,
+        evictions,
+        reuses,
+        fsyncs,
+        fsync_time,
+    ])
+}
@@ -0,0 +1,212 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+use tokio_postgres::error::SqlState;
+
+#[derive(Debug)]
+struct StatStatement {
+    datname: String,
+    usename: String,
+    queryid: i64,
+    calls: i64,
+    total_exec_time: f64,
+    mean_exec_time: Option<f64>,
+    rows: i64,
+    shared_blks_hit: i64,
+    shared_blks_read: i64,
+    shared_blks_dirtied: i64,
+    shared_blks_written: i64,
+    temp_blks_read: i64,
+    temp_blks_written: i64,
+    // Extension version 1.8+.
+    wal_bytes: Option<f64>,
+}
+
+/// Parses an extension version such as "1.10" into (major, minor).
+fn parse_extversion(extversion: &str) -> (u32, u32) {
+    let mut parts = extversion.split('.').map(|part| part.parse().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+/// Exports the `limit` statements with the highest total execution time.
+/// Nothing is exported when the extension is not installed in the
+/// database the exporter connects to or the module is not loaded through
+/// `shared_preload_libraries`.
+pub async fn collect(
+    postgres_client: &Client,
+    limit: i64,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let extension_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                quote_ident(n.nspname),
+                e.extversion
+            FROM
+                pg_extension e
+                JOIN pg_namespace n ON n.oid = e.extnamespace
+            WHERE
+                e.extname = 'pg_stat_statements'")
+        .await?;
+    let extension_row = match postgres_client
+        .query_opt(&extension_statement, &[])
+        .await?
+    {
+        Some(extension_row) => extension_row,
+        None => return Ok(Vec::new()),
+    };
+    let schema: String = extension_row.try_get(0)?;
+    let extversion: String = extension_row.try_get(1)?;
+    // Version 1.8 (shipped with PostgreSQL 13) renamed total_time to
+    // total_exec_time and added the WAL columns. The extension version
+    // matters rather than the server's, since it is only upgraded by
+    // ALTER EXTENSION.
+    let (exec_time_column, wal_bytes_column) = if parse_extversion(&extversion) >= (1, 8) {
+        ("total_exec_time", "sum(wal_bytes)::float8")
+    } else {
+        ("total_time", "NULL::float8")
+    };
+    let stat_statements_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                coalesce(d.datname, '') datname,
+                coalesce(r.rolname, '') usename,
+                s.queryid,
+                sum(s.calls)::bigint calls,
+                sum(s.{0}) total_exec_time,
+                sum(s.{0}) / nullif(sum(s.calls), 0) mean_exec_time,
+                sum(s.rows)::bigint rows,
+                sum(s.shared_blks_hit)::bigint shared_blks_hit,
+                sum(s.shared_blks_read)::bigint shared_blks_read,
+                sum(s.shared_blks_dirtied)::bigint shared_blks_dirtied,
+                sum(s.shared_blks_written)::bigint shared_blks_written,
+                sum(s.temp_blks_read)::bigint temp_blks_read,
+                sum(s.temp_blks_written)::bigint temp_blks_written,
+                {1} wal_bytes
+            FROM
+                {2}.pg_stat_statements s
+                LEFT JOIN pg_database d ON d.oid = s.dbid
+                LEFT JOIN pg_roles r ON r.oid = s.userid
+            WHERE
+                s.queryid IS NOT NULL
+            GROUP BY 1, 2, 3
+            ORDER BY total_exec_time DESC
+            LIMIT $1",
+            exec_time_column, wal_bytes_column, schema
+        ))
+        .await?;
+    // The view raises "must be loaded via shared_preload_libraries" when the
+    // extension is created but the module is not loaded.
+    let stat_statements_rows = match postgres_client
+        .query(&stat_statements_statement, &[&limit])
+        .await
+    {
+        Ok(stat_statements_rows) => stat_statements_rows,
+        Err(e) if e.code() == Some(&SqlState::OBJECT_NOT_IN_PREREQUISITE_STATE) => {
+            return Ok(Vec::new())
+        }
+        Err(e) => return Err(e),
+    };
+    let stat_statements = stat_statements_rows
+        .iter()
+        .map(|row| {
+            Ok(StatStatement {
+                datname: row.try_get(0)?,
+                usename: row.try_get(1)?,
+                queryid: row.try_get(2)?,
+                calls: row.try_get(3)?,
+                total_exec_time: row.try_get(4)?,
+                mean_exec_time: row.try_get(5)?,
+                rows: row.try_get(6)?,
+                shared_blks_hit: row.try_get(7)?,
+                shared_blks_read: row.try_get(8)?,
+                shared_blks_dirtied: row.try_get(9)?,
+                shared_blks_written: row.try_get(10)?,
+                temp_blks_read: row.try_get(11)?,
+                temp_blks_written: row.try_get(12)?,
+                wal_bytes: row.try_get(13)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut calls = MetricFamily::counter(
+        "pg_stat_statements_calls",
+        "Number of times the statement was executed",
+    );
+    let mut total_exec_time = MetricFamily::counter(
+        "pg_stat_statements_exec_time_seconds",
+        "Total time spent executing the statement",
+    )
+    .with_unit("seconds");
+    let mut mean_exec_time = MetricFamily::gauge(
+        "pg_stat_statements_mean_exec_time_seconds",
+        "Mean time spent executing the statement",
+    )
+    .with_unit("seconds");
+    let mut rows = MetricFamily::counter(
+        "pg_stat_statements_rows",
+        "Number of rows retrieved or affected by the statement",
+    );
+    let mut shared_blks_hit = MetricFamily::counter(
+        "pg_stat_statements_shared_blks_hit",
+        "Number of shared block cache hits by the statement",
+    );
+    let mut shared_blks_read = MetricFamily::counter(
+        "pg_stat_statements_shared_blks_read",
+        "Number of shared blocks read by the statement",
+    );
+    let mut shared_blks_dirtied = MetricFamily::counter(
+        "pg_stat_statements_shared_blks_dirtied",
+        "Number of shared blocks dirtied by the statement",
+    );
+    let mut shared_blks_written = MetricFamily::counter(
+        "pg_stat_statements_shared_blks_written",
+        "Number of shared blocks written by the statement",
+    );
+    let mut temp_blks_read = MetricFamily::counter(
+        "pg_stat_statements_temp_blks_read",
+        "Number of temp blocks read by the statement",
+    );
+    let mut temp_blks_written = MetricFamily::counter(
+        "pg_stat_statements_temp_blks_written",
+        "Number of temp blocks written by the statement",
+    );
+    let mut wal_bytes = MetricFamily::counter(
+        "pg_stat_statements_wal_bytes",
+        "Amount of WAL generated by the statement",
+    )
+    .with_unit("bytes");
+    for i in &stat_statements {
+        let queryid = i.queryid.to_string();
+        let labels = [
+            ("datname", i.datname.as_str()),
+            ("usename", i.usename.as_str()),
+            ("queryid", queryid.as_str()),
+        ];
+        calls.sample(&labels, i.calls as f64);
+        total_exec_time.sample(&labels, i.total_exec_time / 1000.0);
+        if let Some(value) = i.mean_exec_time {
+            mean_exec_time.sample(&labels, value / 1000.0);
+        }
+        rows.sample(&labels, i.rows as f64);
+        shared_blks_hit.sample(&labels, i.shared_blks_hit as f64);
+        shared_blks_read.sample(&labels, i.shared_blks_read as f64);
+        shared_blks_dirtied.sample(&labels, i.shared_blks_dirtied as f64);
+        shared_blks_written.sample(&labels, i.shared_blks_written as f64);
+        temp_blks_read.sample(&labels, i.temp_blks_read as f64);
+        temp_blks_written.sample(&labels, i.temp_blks_written as f64);
+        if let Some(value) = i.wal_bytes {
+            wal_bytes.sample(&labels, value);
+        }
+    }
+    Ok(vec![
+        calls,
+        total_exec_time,
+        mean_exec_time,
+        rows,
+        shared_blks_hit,
+        shared_blks_read,
+        shared_blks_dirtied,
+        shared_blks_written,
+        temp_blks_read,
+        temp_blks_written,
+        wal_bytes,
+    ])
+}
@@ -0,0 +1,92 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct TableSize {
+    schemaname: String,
+    relname: String,
+    // The size functions return NULL for tables dropped since the
+    // statistics snapshot was taken.
+    table_size: Option<i64>,
+    indexes_size: Option<i64>,
+    total_relation_size: Option<i64>,
+    toast_size: Option<i64>,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let table_size_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                s.schemaname,
+                s.relname,
+                pg_table_size(s.relid) table_size,
+                pg_indexes_size(s.relid) indexes_size,
+                pg_total_relation_size(s.relid) total_relation_size,
+                CASE WHEN c.reltoastrelid = 0 THEN 0
+                    ELSE pg_total_relation_size(c.reltoastrelid)
+                END toast_size
+            FROM
+                pg_stat_user_tables s
+                JOIN pg_class c ON c.oid = s.relid")
+        .await?;
+    let table_size_rows = postgres_client
+        .query(&table_size_statement, &[])
+        .await?;
+    let table_size = table_size_rows
+        .iter()
+        .map(|row| {
+            Ok(TableSize {
+                schemaname: row.try_get(0)?,
+                relname: row.try_get(1)?,
+                table_size: row.try_get(2)?,
+                indexes_size: row.try_get(3)?,
+                total_relation_size: row.try_get(4)?,
+                toast_size: row.try_get(5)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut table_size_bytes = MetricFamily::gauge(
+        "pg_table_size_bytes",
+        "Disk space used by the table, including TOAST but excluding indexes",
+    )
+    .with_unit("bytes");
+    let mut indexes_size_bytes = MetricFamily::gauge(
+        "pg_table_indexes_size_bytes",
+        "Disk space used by the indexes of the table",
+    )
+    .with_unit("bytes");
+    let mut total_relation_size_bytes = MetricFamily::gauge(
+        "pg_table_total_size_bytes",
+        "Disk space used by the table, including TOAST and indexes",
+    )
+    .with_unit("bytes");
+    let mut toast_size_bytes = MetricFamily::gauge(
+        "pg_table_toast_size_bytes",
+        "Disk space used by the TOAST table of the table and its index",
+    )
+    .with_unit("bytes");
+    for i in &table_size {
+        let labels = [
+            ("schemaname", i.schemaname.as_str()),
+            ("relname", i.relname.as_str()),
+        ];
+        if let Some(value) = i.table_size {
+            table_size_bytes.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.indexes_size {
+            indexes_size_bytes.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.total_relation_size {
+            total_relation_size_bytes.sample(&labels, value as f64);
+        }
+        if let Some(value) = i.toast_size {
+            toast_size_bytes.sample(&labels, value as f64);
+        }
+    }
+    Ok(vec![
+        table_size_bytes,
+        indexes_size_bytes,
+        total_relation_size_bytes,
+        toast_size_bytes,
+    ])
+}
@@ -0,0 +1,70 @@
+use super::wraparound::WRAPAROUND_LIMIT;
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct TableAge {
+    schemaname: String,
+    relname: String,
+    xid_age: i32,
+    mxid_age: i32,
+}
+
+/// Exports the `limit` tables of the database with the oldest
+/// `relfrozenxid`, system catalogs and TOAST tables included.
+pub async fn collect(
+    postgres_client: &Client,
+    limit: i64,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let table_age_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                n.nspname schemaname,
+                c.relname,
+                age(c.relfrozenxid) xid_age,
+                mxid_age(c.relminmxid) mxid_age
+            FROM
+                pg_class c
+                JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE
+                c.relkind IN ('r', 'm', 't')
+            ORDER BY xid_age DESC
+            LIMIT $1")
+        .await?;
+    let table_age_rows = postgres_client
+        .query(&table_age_statement, &[&limit])
+        .await?;
+    let table_age = table_age_rows
+        .iter()
+        .map(|row| {
+            Ok(TableAge {
+                schemaname: row.try_get(0)?,
+                relname: row.try_get(1)?,
+                xid_age: row.try_get(2)?,
+                mxid_age: row.try_get(3)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut xid_age = MetricFamily::gauge(
+        "pg_table_xid_age",
+        "Age of the oldest unfrozen transaction ID in the table",
+    );
+    let mut mxid_age = MetricFamily::gauge(
+        "pg_table_mxid_age",
+        "Age of the oldest unfrozen multixact ID in the table",
+    );
+    let mut xid_wraparound_percent = MetricFamily::gauge(
+        "pg_table_xid_wraparound_percent",
+        "Percentage of transaction IDs consumed towards wraparound in the table",
+    );
+    for i in &table_age {
+        let labels = [
+            ("schemaname", i.schemaname.as_str()),
+            ("relname", i.relname.as_str()),
+        ];
+        xid_age.sample(&labels, i.xid_age as f64);
+        mxid_age.sample(&labels, i.mxid_age as f64);
+        xid_wraparound_percent.sample(&labels, 100.0 * i.xid_age as f64 / WRAPAROUND_LIMIT);
+    }
+    Ok(vec![xid_age, mxid_age, xid_wraparound_percent])
+}
@@ -0,0 +1,115 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct UserIndex {
+    schemaname: String,
+    relname: String,
+    indexrelname: String,
+    idx_scan: i64,
+    idx_tup_read: i64,
+    idx_tup_fetch: i64,
+    idx_blks_read: i64,
+    idx_blks_hit: i64,
+    // NULL for indexes dropped since the statistics snapshot was taken.
+    size: Option<i64>,
+    is_unique: bool,
+    is_primary: bool,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let user_indexes_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                s.schemaname,
+                s.relname,
+                s.indexrelname,
+                s.idx_scan,
+                s.idx_tup_read,
+                s.idx_tup_fetch,
+                io.idx_blks_read,
+                io.idx_blks_hit,
+                pg_relation_size(s.indexrelid) size,
+                i.indisunique,
+                i.indisprimary
+            FROM
+                pg_stat_user_indexes s
+                JOIN pg_statio_user_indexes io ON io.indexrelid = s.indexrelid
+                JOIN pg_index i ON i.indexrelid = s.indexrelid")
+        .await?;
+    let user_indexes_rows = postgres_client
+        .query(&user_indexes_statement, &[])
+        .await?;
+    let user_indexes = user_indexes_rows
+        .iter()
+        .map(|row| {
+            Ok(UserIndex {
+                schemaname: row.try_get(0)?,
+                relname: row.try_get(1)?,
+                indexrelname: row.try_get(2)?,
+                idx_scan: row.try_get(3)?,
+                idx_tup_read: row.try_get(4)?,
+                idx_tup_fetch: row.try_get(5)?,
+                idx_blks_read: row.try_get(6)?,
+                idx_blks_hit: row.try_get(7)?,
+                size: row.try_get(8)?,
+                is_unique: row.try_get(9)?,
+                is_primary: row.try_get(10)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let mut idx_scan = MetricFamily::counter(
+        "pg_stat_user_indexes_idx_scan",
+        "Number of index scans initiated on this index",
+    );
+    let mut idx_tup_read = MetricFamily::counter(
+        "pg_stat_user_indexes_idx_tup_read",
+        "Number of index entries returned by scans on this index",
+    );
+    let mut idx_tup_fetch = MetricFamily::counter(
+        "pg_stat_user_indexes_idx_tup_fetch",
+        "Number of live table rows fetched by simple index scans using this index",
+    );
+    let mut idx_blks_read = MetricFamily::counter(
+        "pg_statio_user_indexes_idx_blks_read",
+        "Number of disk blocks read from this index",
+    );
+    let mut idx_blks_hit = MetricFamily::counter(
+        "pg_statio_user_indexes_idx_blks_hit",
+        "Number of buffer hits in this index",
+    );
+    let mut size = MetricFamily::gauge("pg_index_size_bytes", "Disk space used by the index")
+        .with_unit("bytes");
+    let mut is_unique = MetricFamily::gauge("pg_index_is_unique", "Whether the index is unique");
+    let mut is_primary = MetricFamily::gauge(
+        "pg_index_is_primary",
+        "Whether the index represents the primary key of the table",
+    );
+    for i in &user_indexes {
+        let labels = [
+            ("schemaname", i.schemaname.as_str()),
+            ("relname", i.relname.as_str()),
+            ("indexrelname", i.indexrelname.as_str()),
+        ];
+        idx_scan.sample(&labels, i.idx_scan as f64);
+        idx_tup_read.sample(&labels, i.idx_tup_read as f64);
+        idx_tup_fetch.sample(&labels, i.idx_tup_fetch as f64);
+        idx_blks_read.sample(&labels, i.idx_blks_read as f64);
+        idx_blks_hit.sample(&labels, i.idx_blks_hit as f64);
+        if let Some(value) = i.size {
+            size.sample(&labels, value as f64);
+        }
+        is_unique.sample(&labels, if i.is_unique { 1.0 } else { 0.0 });
+        is_primary.sample(&labels, if i.is_primary { 1.0 } else { 0.0 });
+    }
+    Ok(vec![
+        idx_scan,
+        idx_tup_read,
+        idx_tup_fetch,
+        idx_blks_read,
+        idx_blks_hit,
+        size,
+        is_unique,
+        is_primary,
+    ])
+}
@@ -0,0 +1,153 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+#[derive(Debug)]
+struct StatWal {
+    // NULL on a server before PostgreSQL 10.
+    lsn: Option<f64>,
+    // PostgreSQL 14+.
+    wal_records: Option<i64>,
+    wal_fpi: Option<i64>,
+    wal_bytes: Option<f64>,
+    wal_buffers_full: Option<i64>,
+    // PostgreSQL 14 to 17, later moved to pg_stat_io.
+    wal_write: Option<i64>,
+    wal_sync: Option<i64>,
+    wal_write_time: Option<f64>,
+    wal_sync_time: Option<f64>,
+}
+
+pub async fn collect(
+    postgres_client: &Client,
+    server_version: i32,
+) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    // On a standby the position is the last WAL received from upstream.
+    let lsn_column = if server_version >= 100000 {
+        "pg_wal_lsn_diff(CASE WHEN pg_is_in_recovery()
+                    THEN pg_last_wal_receive_lsn()
+                    ELSE pg_current_wal_lsn() END, '0/0')::float8"
+    } else {
+        "NULL::float8"
+    };
+    let stat_wal_columns = if server_version >= 140000 {
+        "wal_records,
+                wal_fpi,
+                wal_bytes::float8,
+                wal_buffers_full"
+    } else {
+        "NULL::bigint,
+                NULL::bigint,
+                NULL::float8,
+                NULL::bigint"
+    };
+    let stat_wal_io_columns = if (140000..180000).contains(&server_version) {
+        "wal_write,
+                wal_sync,
+                wal_write_time,
+                wal_sync_time"
+    } else {
+        "NULL::bigint,
+                NULL::bigint,
+                NULL::float8,
+                NULL::float8"
+    };
+    let stat_wal_from = if server_version >= 140000 {
+        "FROM
+                pg_stat_wal"
+    } else {
+        ""
+    };
+    let stat_wal_statement = postgres_client
+        .prepare_cached(&format!(
+            "SELECT
+                {},
+                {},
+                {}
+            {}",
+            lsn_column, stat_wal_columns, stat_wal_io_columns, stat_wal_from
+        ))
+        .await?;
+    let row = postgres_client
+        .query_one(&stat_wal_statement, &[])
+        .await?;
+    let stat_wal = StatWal {
+        lsn: row.try_get(0)?,
+        wal_records: row.try_get(1)?,
+        wal_fpi: row.try_get(2)?,
+        wal_bytes: row.try_get(3)?,
+        wal_buffers_full: row.try_get(4)?,
+        wal_write: row.try_get(5)?,
+        wal_sync: row.try_get(6)?,
+        wal_write_time: row.try_get(7)?,
+        wal_sync_time: row.try_get(8)?,
+    };
+    let mut lsn = MetricFamily::counter(
+        "pg_wal_lsn_bytes",
+        "Current write-ahead log location as a byte offset",
+    )
+    .with_unit("bytes");
+    if let Some(value) = stat_wal.lsn {
+        lsn.sample(&[], value);
+    }
+    let mut wal_records = MetricFamily::counter("pg_stat_wal_records", "Number of WAL records generated");
+    if let Some(value) = stat_wal.wal_records {
+        wal_records.sample(&[], value as f64);
+    }
+    let mut wal_fpi = MetricFamily::counter("pg_stat_wal_fpi", "Number of WAL full page images generated");
+    if let Some(value) = stat_wal.wal_fpi {
+        wal_fpi.sample(&[], value as f64);
+    }
+    let mut wal_bytes = MetricFamily::counter("pg_stat_wal_bytes", "Amount of WAL generated")
+        .with_unit("bytes");
+    if let Some(value) = stat_wal.wal_bytes {
+        wal_bytes.sample(&[], value);
+    }
+    let mut wal_buffers_full = MetricFamily::counter(
+        "pg_stat_wal_buffers_full",
+        "Number of times WAL data was written to disk because WAL buffers became full",
+    );
+    if let Some(value) = stat_wal.wal_buffers_full {
+        wal_buffers_full.sample(&[], value as f64);
+    }
+    let mut wal_write = MetricFamily::counter(
+        "pg_stat_wal_write",
+        "Number of times WAL buffers were written out to disk",
+    );
+    if let Some(value) = stat_wal.wal_write {
+        wal_write.sample(&[], value as f64);
+    }
+    let mut wal_sync = MetricFamily::counter(
+        "pg_stat_wal_sync",
+        "Number of times WAL files were synced to disk",
+    );
+    if let Some(value) = stat_wal.wal_sync {
+        wal_sync.sample(&[], value as f64);
+    }
+    let mut wal_write_time = MetricFamily::counter(
+        "pg_stat_wal_write_time_seconds",
+        "Time spent writing WAL buffers to disk, when track_wal_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    if let Some(value) = stat_wal.wal_write_time {
+        wal_write_time.sample(&[], value / 1000.0);
+    }
+    let mut wal_sync_time = MetricFamily::counter(
+        "pg_stat_wal_sync_time_seconds",
+        "Time spent syncing WAL files to disk, when track_wal_io_timing is enabled",
+    )
+    .with_unit("seconds");
+    if let Some(value) = stat_wal.wal_sync_time {
+        wal_sync_time.sample(&[], value / 1000.0);
+    }
+    Ok(vec![
+        lsn,
+        wal_records,
+        wal_fpi,
+        wal_bytes,
+        wal_buffers_full,
+        wal_write,
+        wal_sync,
+        wal_write_time,
+        wal_sync_time,
+    ])
+}
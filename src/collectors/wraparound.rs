@@ -0,0 +1,101 @@
+use crate::exposition::MetricFamily;
+use crate::pool::Client;
+
+/// Transaction and multixact IDs are compared modulo 2^32, so at most 2^31
+/// of them can be in the past before the server stops assigning new ones.
+pub const WRAPAROUND_LIMIT: f64 = 2147483648.0;
+
+#[derive(Debug)]
+struct DatabaseAge {
+    datname: String,
+    xid_age: i32,
+    mxid_age: i32,
+}
+
+#[derive(Debug)]
+struct FreezeSettings {
+    autovacuum_freeze_max_age: i64,
+    autovacuum_multixact_freeze_max_age: i64,
+}
+
+pub async fn collect(postgres_client: &Client) -> Result<Vec<MetricFamily>, tokio_postgres::Error> {
+    let database_age_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                datname,
+                age(datfrozenxid) xid_age,
+                mxid_age(datminmxid) mxid_age
+            FROM
+                pg_database")
+        .await?;
+    let database_age_rows = postgres_client
+        .query(&database_age_statement, &[])
+        .await?;
+    let database_age = database_age_rows
+        .iter()
+        .map(|row| {
+            Ok(DatabaseAge {
+                datname: row.try_get(0)?,
+                xid_age: row.try_get(1)?,
+                mxid_age: row.try_get(2)?,
+            })
+        })
+        .collect::<Result<Vec<_>, tokio_postgres::Error>>()?;
+    let freeze_settings_statement = postgres_client
+        .prepare_cached(
+            "SELECT
+                current_setting('autovacuum_freeze_max_age')::bigint autovacuum_freeze_max_age,
+                current_setting('autovacuum_multixact_freeze_max_age')::bigint autovacuum_multixact_freeze_max_age")
+        .await?;
+    let freeze_settings_row = postgres_client
+        .query_one(&freeze_settings_statement, &[])
+        .await?;
+    let freeze_settings = FreezeSettings {
+        autovacuum_freeze_max_age: freeze_settings_row.try_get(0)?,
+        autovacuum_multixact_freeze_max_age: freeze_settings_row.try_get(1)?,
+    };
+    let mut xid_age = MetricFamily::gauge(
+        "pg_database_xid_age",
+        "Age of the oldest unfrozen transaction ID in the database",
+    );
+    let mut mxid_age = MetricFamily::gauge(
+        "pg_database_mxid_age",
+        "Age of the oldest unfrozen multixact ID in the database",
+    );
+    let mut xid_wraparound_percent = MetricFamily::gauge(
+        "pg_database_xid_wraparound_percent",
+        "Percentage of transaction IDs consumed towards wraparound in the database",
+    );
+    let mut mxid_wraparound_percent = MetricFamily::gauge(
+        "pg_database_mxid_wraparound_percent",
+        "Percentage of multixact IDs consumed towards wraparound in the database",
+    );
+    for i in &database_age {
+        let labels = [("datname", i.datname.as_str())];
+        xid_age.sample(&labels, i.xid_age as f64);
+        mxid_age.sample(&labels, i.mxid_age as f64);
+        xid_wraparound_percent.sample(&labels, 100.0 * i.xid_age as f64 / WRAPAROUND_LIMIT);
+        mxid_wraparound_percent.sample(&labels, 100.0 * i.mxid_age as f64 / WRAPAROUND_LIMIT);
+    }
+    let mut autovacuum_freeze_max_age = MetricFamily::gauge(
+        "pg_autovacuum_freeze_max_age",
+        "Transaction ID age at which autovacuum is forced to freeze a table",
+    );
+    autovacuum_freeze_max_age.sample(&[], freeze_settings.autovacuum_freeze_max_age as f64);
+    let mut autovacuum_multixact_freeze_max_age = MetricFamily::gauge(
+        "pg_autovacuum_multixact_freeze_max_age",
+        "Multixact ID age at which autovacuum is forced to freeze a table",
+    );
+    autovacuum_multixact_freeze_max_age.sample(
+        &[],
+        freeze_settings.autovacuum_multixact_freeze_max_age as f64,
+    );
+    Ok(vec![
+        xid_age,
+        mxid_age,
+        xid_wraparound_percent,
+        mxid_wraparound_percent,
+        autovacuum_freeze_max_age,
+        autovacuum_multixact_freeze_max_age,
+    ])
+}
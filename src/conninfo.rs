@@ -0,0 +1,384 @@
+use crate::tls::{self, SslMode, TlsOptions};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the password for new connections comes from.
+#[derive(Debug, Clone)]
+pub enum PasswordSource {
+    /// The `password` parameter of the connection, if any.
+    Params,
+    /// A file holding only the password, read again for every connection.
+    File(PathBuf),
+    /// The libpq password file, searched again for every connection.
+    PgPass(Params),
+}
+
+impl PasswordSource {
+    /// An explicit password file wins over the `password` parameter, which
+    /// in turn wins over the libpq password file.
+    pub fn for_params(params: &Params, password_file: Option<&Path>) -> PasswordSource {
+        if let Some(path) = password_file {
+            PasswordSource::File(path.to_path_buf())
+        } else if params.contains_key("password") {
+            PasswordSource::Params
+        } else {
+            PasswordSource::PgPass(params.clone())
+        }
+    }
+
+    pub fn read(&self) -> Result<Option<String>, String> {
+        match self {
+            PasswordSource::Params => Ok(None),
+            PasswordSource::File(path) => fs::read_to_string(path)
+                .map(|password| Some(password.trim_end_matches(['\r', '\n']).to_string()))
+                .map_err(|e| format!("failed to read password file {}: {}", path.display(), e)),
+            PasswordSource::PgPass(params) => Ok(pgpass_password(params)),
+        }
+    }
+}
+
+/// libpq connection parameters keyed by their conninfo keyword.
+pub type Params = BTreeMap<String, String>;
+
+/// libpq environment variables and the keywords they provide defaults for.
+const ENV_VARS: &[(&str, &str)] = &[
+    ("PGHOST", "host"),
+    ("PGPORT", "port"),
+    ("PGUSER", "user"),
+    ("PGPASSWORD", "password"),
+    ("PGDATABASE", "dbname"),
+    ("PGPASSFILE", "passfile"),
+    ("PGOPTIONS", "options"),
+    ("PGAPPNAME", "application_name"),
+    ("PGCONNECT_TIMEOUT", "connect_timeout"),
+    ("PGTARGETSESSIONATTRS", "target_session_attrs"),
+    ("PGCHANNELBINDING", "channel_binding"),
+    ("PGSSLMODE", "sslmode"),
+    ("PGSSLROOTCERT", "sslrootcert"),
+    ("PGSSLCERT", "sslcert"),
+    ("PGSSLKEY", "sslkey"),
+];
+
+/// Keywords handled by the exporter itself rather than by tokio-postgres.
+const LOCAL_KEYS: &[&str] = &["passfile", "sslmode", "sslrootcert", "sslcert", "sslkey"];
+
+pub fn from_env() -> Params {
+    ENV_VARS
+        .iter()
+        .filter_map(|(var, key)| {
+            std::env::var(var)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(|value| (key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Parses either a `key=value` connection string or a `postgresql://` URI.
+pub fn parse(dsn: &str) -> Result<Params, String> {
+    let dsn = dsn.trim();
+    if let Some(rest) = dsn
+        .strip_prefix("postgresql://")
+        .or_else(|| dsn.strip_prefix("postgres://"))
+    {
+        parse_uri(rest)
+    } else {
+        parse_key_value(dsn)
+    }
+}
+
+fn parse_key_value(dsn: &str) -> Result<Params, String> {
+    let mut params = Params::new();
+    let mut chars = dsn.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(params);
+        }
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && !c.is_whitespace()) {
+            key.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next() != Some('=') {
+            return Err(format!("missing \"=\" after \"{}\" in connection string", key));
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if_eq(&'\'').is_some() {
+            loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some('\\') => value.extend(chars.next()),
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(format!("unterminated quoted value for \"{}\"", key));
+                    }
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == '\\' {
+                    value.extend(chars.next());
+                } else {
+                    value.push(c);
+                }
+            }
+        }
+        params.insert(key, value);
+    }
+}
+
+fn parse_uri(rest: &str) -> Result<Params, String> {
+    let mut params = Params::new();
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (rest, None),
+    };
+    let (authority, dbname) = match rest.split_once('/') {
+        Some((authority, dbname)) => (authority, Some(dbname)),
+        None => (rest, None),
+    };
+    let hostspec = match authority.rsplit_once('@') {
+        Some((userspec, hostspec)) => {
+            let (user, password) = match userspec.split_once(':') {
+                Some((user, password)) => (user, Some(password)),
+                None => (userspec, None),
+            };
+            if !user.is_empty() {
+                params.insert("user".to_string(), percent_decode(user)?);
+            }
+            if let Some(password) = password {
+                params.insert("password".to_string(), percent_decode(password)?);
+            }
+            hostspec
+        }
+        None => authority,
+    };
+    let mut hosts = Vec::new();
+    let mut ports = Vec::new();
+    for host in hostspec.split(',').filter(|host| !host.is_empty()) {
+        let (host, port) = if let Some(bracketed) = host.strip_prefix('[') {
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| format!("invalid IPv6 host \"{}\" in URI", host))?;
+            (host, rest.strip_prefix(':'))
+        } else {
+            match host.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host, None),
+            }
+        };
+        hosts.push(percent_decode(host)?);
+        ports.push(port.unwrap_or("").to_string());
+    }
+    if !hosts.is_empty() {
+        params.insert("host".to_string(), hosts.join(","));
+    }
+    if ports.iter().any(|port| !port.is_empty()) {
+        params.insert("port".to_string(), ports.join(","));
+    }
+    if let Some(dbname) = dbname.filter(|dbname| !dbname.is_empty()) {
+        params.insert("dbname".to_string(), percent_decode(dbname)?);
+    }
+    for pair in query.unwrap_or("").split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("missing \"=\" in URI parameter \"{}\"", pair))?;
+        params.insert(percent_decode(key)?, percent_decode(value)?);
+    }
+    Ok(params)
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid percent-encoding in \"{}\"", value))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| format!("invalid UTF-8 in \"{}\"", value))
+}
+
+/// Builds the tokio-postgres configuration from everything except the
+/// keywords listed in `LOCAL_KEYS`. Without a host the exporter deliberately
+/// connects over TCP to localhost: libpq would use its compiled-in Unix
+/// socket directory, which differs between builds. The user defaults to the
+/// OS user as in libpq.
+pub fn to_config(params: &Params) -> Result<tokio_postgres::Config, String> {
+    let mut conninfo: Vec<String> = params
+        .iter()
+        .filter(|(key, _)| !LOCAL_KEYS.contains(&key.as_str()))
+        .map(|(key, value)| format!("{}='{}'", key, quote(value)))
+        .collect();
+    if !params.contains_key("host") {
+        conninfo.push("host=localhost".to_string());
+    }
+    if !params.contains_key("user") {
+        if let Ok(user) = std::env::var("USER") {
+            conninfo.push(format!("user='{}'", quote(&user)));
+        }
+    }
+    let mut config: tokio_postgres::Config = conninfo
+        .join(" ")
+        .parse()
+        .map_err(|e| format!("invalid connection parameters: {}", e))?;
+    config.ssl_mode(tls_options(params)?.ssl_mode.to_postgres());
+    Ok(config)
+}
+
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+pub fn tls_options(params: &Params) -> Result<TlsOptions, String> {
+    Ok(TlsOptions {
+        ssl_mode: match params.get("sslmode") {
+            Some(mode) => tls::parse_ssl_mode(mode)?,
+            None => SslMode::Prefer,
+        },
+        root_cert: params.get("sslrootcert").cloned(),
+        cert: params.get("sslcert").cloned(),
+        key: params.get("sslkey").cloned(),
+    })
+}
+
+/// Looks up the password in the password file the way libpq does, using
+/// the first host and port of the connection.
+pub fn pgpass_password(params: &Params) -> Option<String> {
+    let path = match params.get("passfile") {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".pgpass"),
+    };
+    let contents = fs::read_to_string(&path).ok()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).ok()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            eprintln!(
+                "password file {} has group or world access; permissions should be u=rw (0600) or less",
+                path.display()
+            );
+            return None;
+        }
+    }
+    let host = params
+        .get("host")
+        .and_then(|hosts| hosts.split(',').next())
+        .filter(|host| !host.is_empty() && !host.starts_with('/'))
+        .unwrap_or("localhost");
+    let port = params
+        .get("port")
+        .and_then(|ports| ports.split(',').next())
+        .filter(|port| !port.is_empty())
+        .unwrap_or("5432");
+    let user = params
+        .get("user")
+        .cloned()
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_default();
+    let dbname = params.get("dbname").unwrap_or(&user);
+    let wanted = [host, port, dbname.as_str(), user.as_str()];
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(split_pgpass_line)
+        .find(|fields| {
+            fields.len() == 5
+                && fields
+                    .iter()
+                    .zip(wanted.iter())
+                    .all(|(field, wanted)| field == "*" || field == wanted)
+        })
+        .map(|mut fields| fields.remove(4))
+}
+
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => fields.last_mut().unwrap().extend(chars.next()),
+            ':' if fields.len() < 5 => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_key_value_with_quoted_values() {
+        let params = parse(r"host=db1 port = 5433 password='p a\'ss' application_name=a\ b").unwrap();
+        assert_eq!(params["host"], "db1");
+        assert_eq!(params["port"], "5433");
+        assert_eq!(params["password"], "p a'ss");
+        assert_eq!(params["application_name"], "a b");
+    }
+
+    #[test]
+    fn parse_key_value_errors() {
+        assert!(parse("host").is_err());
+        assert!(parse("password='unterminated").is_err());
+    }
+
+    #[test]
+    fn parse_uri_with_ipv6_host_and_parameters() {
+        let params = parse("postgresql://user:p%40ss@[::1]:5433/db?sslmode=require").unwrap();
+        assert_eq!(params["user"], "user");
+        assert_eq!(params["password"], "p@ss");
+        assert_eq!(params["host"], "::1");
+        assert_eq!(params["port"], "5433");
+        assert_eq!(params["dbname"], "db");
+        assert_eq!(params["sslmode"], "require");
+        assert_eq!(params.len(), 6);
+    }
+
+    #[test]
+    fn parse_uri_with_several_hosts() {
+        let params = parse_uri("db1,db2:5433/app").unwrap();
+        assert_eq!(params["host"], "db1,db2");
+        assert_eq!(params["port"], ",5433");
+        assert_eq!(params["dbname"], "app");
+        assert!(!params.contains_key("user"));
+    }
+
+    #[test]
+    fn parse_uri_errors() {
+        assert!(parse_uri("[::1:5432/db").is_err());
+        assert!(parse_uri("db1/app?sslmode").is_err());
+    }
+
+    #[test]
+    fn percent_decode_values() {
+        assert_eq!(percent_decode("p%40ss%2F%c3%a9").unwrap(), "p@ss/é");
+        assert_eq!(percent_decode("plain").unwrap(), "plain");
+        assert!(percent_decode("%4").is_err());
+        assert!(percent_decode("%zz").is_err());
+        assert!(percent_decode("%ff").is_err());
+    }
+
+    #[test]
+    fn split_pgpass_line_with_escapes() {
+        assert_eq!(
+            split_pgpass_line(r"db1:5432:*:user\:name:pa\:ss:wo\\rd"),
+            ["db1", "5432", "*", "user:name", r"pa:ss:wo\rd"]
+        );
+        assert_eq!(split_pgpass_line("db1:5432"), ["db1", "5432"]);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::pool::Client;
+use regex::Regex;
+
+/// Decides which databases autodiscovery scrapes. Both patterns must match
+/// the whole database name.
+#[derive(Debug)]
+pub struct DatabaseFilter {
+    include: Option<Regex>,
+    exclude: Option<Regex>,
+}
+
+impl DatabaseFilter {
+    pub fn new(include: Option<&str>, exclude: Option<&str>) -> Result<DatabaseFilter, String> {
+        Ok(DatabaseFilter {
+            include: include.map(anchored).transpose()?,
+            exclude: exclude.map(anchored).transpose()?,
+        })
+    }
+
+    pub fn matches(&self, datname: &str) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|include| include.is_match(datname))
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|exclude| exclude.is_match(datname))
+    }
+}
+
+fn anchored(pattern: &str) -> Result<Regex, String> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| format!("invalid database pattern '{}': {}", pattern, e))
+}
+
+/// Lists the databases that accept connections, that the exporter may
+/// connect to and that pass `filter`.
+pub async fn list_databases(
+    postgres_client: &Client,
+    filter: &DatabaseFilter,
+) -> Result<Vec<String>, tokio_postgres::Error> {
+    let databases_statement = postgres_client
+        .prepare_cached(
+            "SELECT datname
+            FROM pg_database
+            WHERE datallowconn AND NOT datistemplate
+                AND has_database_privilege(oid, 'CONNECT')
+            ORDER BY datname")
+        .await?;
+    let databases_rows = postgres_client
+        .query(&databases_statement, &[])
+        .await?;
+    let mut datnames = Vec::new();
+    for row in &databases_rows {
+        let datname: String = row.try_get(0)?;
+        if filter.matches(&datname) {
+            datnames.push(datname);
+        }
+    }
+    Ok(datnames)
+}
@@ -0,0 +1,358 @@
+use std::fmt::Write;
+
+pub const TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    OpenMetrics,
+}
+
+impl Format {
+    /// Picks the exposition format from an `Accept` header, preferring
+    /// OpenMetrics only when the client ranks it at least as high as the
+    /// plain text format.
+    pub fn from_accept(accept: Option<&str>) -> Format {
+        let accept = match accept {
+            Some(accept) => accept,
+            None => return Format::Text,
+        };
+        let mut openmetrics_q: f32 = 0.0;
+        let mut text_q: f32 = 0.0;
+        for media_range in accept.split(',') {
+            let mut params = media_range.split(';').map(str::trim);
+            let media_type = params.next().unwrap_or("").to_ascii_lowercase();
+            let q = params
+                .filter_map(|param| param.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            match media_type.as_str() {
+                "application/openmetrics-text" => openmetrics_q = openmetrics_q.max(q),
+                "text/plain" | "text/*" | "*/*" => text_q = text_q.max(q),
+                _ => {}
+            }
+        }
+        if openmetrics_q > 0.0 && openmetrics_q >= text_q {
+            Format::OpenMetrics
+        } else {
+            Format::Text
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Text => TEXT_CONTENT_TYPE,
+            Format::OpenMetrics => OPENMETRICS_CONTENT_TYPE,
+        }
+    }
+
+    pub fn render(&self, families: &[MetricFamily]) -> String {
+        match self {
+            Format::Text => render_text(families),
+            Format::OpenMetrics => render_openmetrics(families),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Counter,
+    Gauge,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Counter => "counter",
+            MetricType::Gauge => "gauge",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Sample {
+    pub labels: Vec<(String, String)>,
+    pub value: f64,
+}
+
+/// A single metric family. `name` is the family name without the `_total`
+/// suffix; counters get the suffix appended when rendered. When `unit` is
+/// set, `name` must end with `_<unit>`.
+#[derive(Debug)]
+pub struct MetricFamily {
+    pub name: String,
+    pub help: String,
+    pub kind: MetricType,
+    pub unit: Option<&'static str>,
+    pub samples: Vec<Sample>,
+}
+
+impl MetricFamily {
+    pub fn new(name: impl Into<String>, help: impl Into<String>, kind: MetricType) -> Self {
+        MetricFamily {
+            name: name.into(),
+            help: help.into(),
+            kind,
+            unit: None,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn with_unit(mut self, unit: &'static str) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    pub fn counter(name: impl Into<String>, help: impl Into<String>) -> Self {
+        Self::new(name, help, MetricType::Counter)
+    }
+
+    pub fn gauge(name: impl Into<String>, help: impl Into<String>) -> Self {
+        Self::new(name, help, MetricType::Gauge)
+    }
+
+    pub fn sample(&mut self, labels: &[(&str, &str)], value: f64) {
+        self.samples.push(Sample {
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            value,
+        });
+    }
+
+    /// Prepends a label to every sample, e.g. to tell apart the same
+    /// family collected from several databases.
+    pub fn add_label(&mut self, name: &str, value: &str) {
+        for sample in &mut self.samples {
+            sample.labels.insert(0, (name.to_string(), value.to_string()));
+        }
+    }
+
+    /// Name used on sample lines.
+    pub fn sample_name(&self) -> String {
+        match self.kind {
+            MetricType::Counter => format!("{}_total", self.name),
+            MetricType::Gauge => self.name.clone(),
+        }
+    }
+}
+
+/// Combines families sharing a name into one, keeping the order in which
+/// names first appear, as each family may only be exposed once.
+pub fn merge(families: Vec<MetricFamily>) -> Vec<MetricFamily> {
+    let mut merged: Vec<MetricFamily> = Vec::new();
+    for family in families {
+        match merged.iter_mut().find(|existing| existing.name == family.name) {
+            Some(existing) => existing.samples.extend(family.samples),
+            None => merged.push(family),
+        }
+    }
+    merged
+}
+
+/// Renders families in the Prometheus text exposition format 0.0.4.
+pub fn render_text(families: &[MetricFamily]) -> String {
+    let mut result = String::new();
+    for family in families {
+        if family.samples.is_empty() {
+            continue;
+        }
+        let name = family.sample_name();
+        writeln!(result, "# HELP {} {}", name, escape_help(&family.help)).unwrap();
+        writeln!(result, "# TYPE {} {}", name, family.kind.as_str()).unwrap();
+        for sample in &family.samples {
+            writeln!(
+                result,
+                "{}{} {}",
+                name,
+                format_labels(&sample.labels),
+                format_value(sample.value)
+            )
+            .unwrap();
+        }
+    }
+    result
+}
+
+/// Renders families in the OpenMetrics 1.0 text format.
+pub fn render_openmetrics(families: &[MetricFamily]) -> String {
+    let mut result = String::new();
+    for family in families {
+        if family.samples.is_empty() {
+            continue;
+        }
+        writeln!(result, "# TYPE {} {}", family.name, family.kind.as_str()).unwrap();
+        if let Some(unit) = family.unit {
+            writeln!(result, "# UNIT {} {}", family.name, unit).unwrap();
+        }
+        writeln!(
+            result,
+            "# HELP {} {}",
+            family.name,
+            escape_openmetrics_help(&family.help)
+        )
+        .unwrap();
+        let name = family.sample_name();
+        for sample in &family.samples {
+            writeln!(
+                result,
+                "{}{} {}",
+                name,
+                format_labels(&sample.labels),
+                format_value(sample.value)
+            )
+            .unwrap();
+        }
+    }
+    result.push_str("# EOF\n");
+    result
+}
+
+pub fn format_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+pub fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn escape_openmetrics_help(help: &str) -> String {
+    escape_help(help).replace('"', "\\\"")
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn families() -> Vec<MetricFamily> {
+        let mut calls = MetricFamily::counter("pg_calls", "Number of \"calls\"\nso far");
+        calls.sample(&[("datname", "a\"b\\c\nd")], 3.0);
+        let mut time = MetricFamily::gauge("pg_time_seconds", "Time").with_unit("seconds");
+        time.sample(&[], 1.5);
+        time.sample(&[("k", "v")], f64::NAN);
+        let empty = MetricFamily::gauge("pg_empty", "Never sampled");
+        vec![calls, time, empty]
+    }
+
+    #[test]
+    fn from_accept_ranks_by_q_value() {
+        assert_eq!(Format::from_accept(None), Format::Text);
+        assert_eq!(Format::from_accept(Some("text/plain")), Format::Text);
+        assert_eq!(
+            Format::from_accept(Some("application/openmetrics-text; version=1.0.0")),
+            Format::OpenMetrics
+        );
+        assert_eq!(
+            Format::from_accept(Some(
+                "application/openmetrics-text;version=1.0.0;q=0.5,text/plain;version=0.0.4;q=0.4,*/*;q=0.1"
+            )),
+            Format::OpenMetrics
+        );
+        assert_eq!(
+            Format::from_accept(Some("application/openmetrics-text;q=0.3,text/plain;q=0.7")),
+            Format::Text
+        );
+        assert_eq!(
+            Format::from_accept(Some("Application/OpenMetrics-Text;q=0.5, */*;q=0.5")),
+            Format::OpenMetrics
+        );
+        assert_eq!(
+            Format::from_accept(Some("application/openmetrics-text;q=0.5,*/*")),
+            Format::Text
+        );
+        assert_eq!(
+            Format::from_accept(Some("application/openmetrics-text;q=0")),
+            Format::Text
+        );
+        assert_eq!(Format::from_accept(Some("*/*")), Format::Text);
+    }
+
+    #[test]
+    fn render_text_format() {
+        assert_eq!(
+            render_text(&families()),
+            "# HELP pg_calls_total Number of \"calls\"\\nso far\n\
+             # TYPE pg_calls_total counter\n\
+             pg_calls_total{datname=\"a\\\"b\\\\c\\nd\"} 3\n\
+             # HELP pg_time_seconds Time\n\
+             # TYPE pg_time_seconds gauge\n\
+             pg_time_seconds 1.5\n\
+             pg_time_seconds{k=\"v\"} NaN\n"
+        );
+    }
+
+    #[test]
+    fn render_openmetrics_format() {
+        assert_eq!(
+            render_openmetrics(&families()),
+            "# TYPE pg_calls counter\n\
+             # HELP pg_calls Number of \\\"calls\\\"\\nso far\n\
+             pg_calls_total{datname=\"a\\\"b\\\\c\\nd\"} 3\n\
+             # TYPE pg_time_seconds gauge\n\
+             # UNIT pg_time_seconds seconds\n\
+             # HELP pg_time_seconds Time\n\
+             pg_time_seconds 1.5\n\
+             pg_time_seconds{k=\"v\"} NaN\n\
+             # EOF\n"
+        );
+        assert_eq!(render_openmetrics(&[]), "# EOF\n");
+    }
+
+    #[test]
+    fn format_special_values() {
+        assert_eq!(format_value(f64::INFINITY), "+Inf");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_value(-0.25), "-0.25");
+        assert_eq!(format_labels(&[]), "");
+    }
+
+    #[test]
+    fn merge_keeps_first_appearance_order() {
+        let mut a = MetricFamily::gauge("a", "A");
+        a.sample(&[("db", "1")], 1.0);
+        let b = MetricFamily::gauge("b", "B");
+        let mut a2 = MetricFamily::gauge("a", "A");
+        a2.sample(&[("db", "2")], 2.0);
+        a2.add_label("datname", "x");
+        let merged = merge(vec![a, b, a2]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].name, "a");
+        assert_eq!(merged[1].name, "b");
+        assert_eq!(
+            merged[0].samples[1].labels,
+            [
+                ("datname".to_string(), "x".to_string()),
+                ("db".to_string(), "2".to_string())
+            ]
+        );
+    }
+}
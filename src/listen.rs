@@ -0,0 +1,100 @@
+use axum::Router;
+use hyper::server::conn::AddrIncoming;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+
+#[derive(Debug, Clone)]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Parses `host:port`, `[ipv6]:port`, `:port` (all IPv4 interfaces) or
+/// `unix:/path/to/socket`.
+pub fn parse_listen_address(value: &str) -> Result<ListenAddress, String> {
+    if let Some(path) = value.strip_prefix("unix:") {
+        #[cfg(unix)]
+        return Ok(ListenAddress::Unix(PathBuf::from(path)));
+        #[cfg(not(unix))]
+        return Err(format!("unix sockets are not supported on this platform: {}", path));
+    }
+    let value = match value.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => value.to_string(),
+    };
+    value
+        .parse()
+        .map(ListenAddress::Tcp)
+        .map_err(|e| format!("invalid listen address '{}': {}", value, e))
+}
+
+/// A bound listen address, ready to serve.
+pub enum Listener {
+    Tcp(SocketAddr, hyper::server::Builder<AddrIncoming>),
+    #[cfg(unix)]
+    Unix(PathBuf, UnixListener),
+}
+
+/// Binds `address` without accepting connections yet, so that every
+/// address can be checked before any of them is served.
+pub fn bind(address: &ListenAddress) -> Result<Listener, String> {
+    match address {
+        ListenAddress::Tcp(addr) => axum::Server::try_bind(addr)
+            .map(|builder| Listener::Tcp(*addr, builder))
+            .map_err(|e| format!("failed to listen on {}: {}", addr, e)),
+        #[cfg(unix)]
+        ListenAddress::Unix(path) => {
+            remove_stale_socket(path)?;
+            UnixListener::bind(path)
+                .map(|listener| Listener::Unix(path.clone(), listener))
+                .map_err(|e| format!("failed to listen on {}: {}", path.display(), e))
+        }
+    }
+}
+
+/// Removes a socket left behind by a previous run, which would make bind
+/// fail. A socket that still accepts connections belongs to a running
+/// process and is left alone.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    let is_socket = std::fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.file_type().is_socket());
+    if !is_socket {
+        return Ok(());
+    }
+    match UnixStream::connect(path) {
+        Ok(_) => Err(format!("socket {} is in use by another process", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => std::fs::remove_file(path)
+            .map_err(|e| format!("failed to remove stale socket {}: {}", path.display(), e)),
+        // Let bind report whatever else is wrong with the path.
+        Err(_) => Ok(()),
+    }
+}
+
+pub async fn serve(listener: Listener, app: Router) -> Result<(), String> {
+    match listener {
+        Listener::Tcp(addr, builder) => builder
+            .serve(app.into_make_service())
+            .await
+            .map_err(|e| format!("server on {} failed: {}", addr, e)),
+        #[cfg(unix)]
+        Listener::Unix(path, listener) => {
+            let accept = hyper::server::accept::poll_fn(move |cx| {
+                listener
+                    .poll_accept(cx)
+                    .map(|result| Some(result.map(|(stream, _)| stream)))
+            });
+            axum::Server::builder(accept)
+                .serve(app.into_make_service())
+                .await
+                .map_err(|e| format!("server on {} failed: {}", path.display(), e))
+        }
+    }
+}
@@ -1,8 +1,31 @@
+mod collectors;
+mod conninfo;
+mod discovery;
+mod exposition;
+mod listen;
+mod pool;
+mod probe;
+mod target;
+mod tls;
+
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Router;
-use rust_decimal::prelude::*;
-use std::net::SocketAddr;
-use tokio_postgres::NoTls;
-use clap::{Command, Arg};
+use collectors::CollectorOptions;
+use conninfo::Params;
+use deadpool_postgres::RecyclingMethod;
+use discovery::DatabaseFilter;
+use exposition::{Format, MetricFamily};
+use listen::ListenAddress;
+use pool::PoolOptions;
+use probe::{ProbeQuery, Prober};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use target::Target;
+use tokio::task::JoinSet;
+use clap::{Arg, ArgAction, Command};
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -11,247 +34,295 @@ lazy_static! {
         .author("batman")
         .arg(
             Arg::new("host")
-                .help("Postgresql host")
-                .required(true),
+                .long("host")
+                .help("Postgresql host"),
         )
         .arg(
             Arg::new("database")
-                .help("Postgresql database")
-                .required(true),
+                .long("database")
+                .help("Postgresql database"),
         )
         .arg(
             Arg::new("user")
-                .help("Postgresql user")
-                .required(true),
+                .long("user")
+                .help("Postgresql user"),
         )
         .arg(
             Arg::new("password")
-                .help("Postgresql password")
-                .required(true),
+                .long("password")
+                .help("Postgresql password, prefer --password-file or PGPASSWORD"),
+        )
+        .arg(
+            Arg::new("password-file")
+                .long("password-file")
+                .env("DATA_SOURCE_PASS_FILE")
+                .help("File containing the Postgresql password, re-read for every new connection")
+                .conflicts_with("password"),
+        )
+        .arg(
+            Arg::new("dsn")
+                .long("dsn")
+                .env("DATA_SOURCE_NAME")
+                .hide_env_values(true)
+                .help("Connection string in key=value or postgresql:// URI form"),
+        )
+        .arg(
+            Arg::new("sslmode")
+                .long("sslmode")
+                .help("TLS mode for the PostgreSQL connection [default: prefer]")
+                .value_parser(["disable", "prefer", "require", "verify-ca", "verify-full"]),
+        )
+        .arg(
+            Arg::new("sslrootcert")
+                .long("sslrootcert")
+                .help("PEM file with the certificate authorities trusted to sign the server certificate"),
+        )
+        .arg(
+            Arg::new("sslcert")
+                .long("sslcert")
+                .help("PEM file with the client certificate")
+                .requires("sslkey"),
+        )
+        .arg(
+            Arg::new("sslkey")
+                .long("sslkey")
+                .help("PEM file with the PKCS#8 private key of the client certificate")
+                .requires("sslcert"),
+        )
+        .arg(
+            Arg::new("web.listen-address")
+                .long("web.listen-address")
+                .help("Address to listen on: host:port, [ipv6]:port, :port or unix:/path/to/socket; may be repeated")
+                .value_parser(listen::parse_listen_address)
+                .action(ArgAction::Append)
+                .default_value("0.0.0.0:8080"),
+        )
+        .arg(
+            Arg::new("web.telemetry-path")
+                .long("web.telemetry-path")
+                .help("Path under which to expose metrics")
+                .value_parser(parse_telemetry_path)
+                .default_value("/metrics"),
+        )
+        .arg(
+            Arg::new("config.file")
+                .long("config.file")
+                .help("YAML file with auth modules used by the /probe endpoint"),
+        )
+        .arg(
+            Arg::new("auto-discover-databases")
+                .long("auto-discover-databases")
+                .help("Run the per-database collectors on every database of the cluster, labelled with datname")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("include-databases")
+                .long("include-databases")
+                .help("Regular expression matching the whole name of databases to discover")
+                .requires("auto-discover-databases"),
+        )
+        .arg(
+            Arg::new("exclude-databases")
+                .long("exclude-databases")
+                .help("Regular expression matching the whole name of databases to skip during discovery")
+                .requires("auto-discover-databases"),
+        )
+        .arg(
+            Arg::new("pool.max-size")
+                .long("pool.max-size")
+                .help("Maximum number of pooled connections to PostgreSQL")
+                .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+                .default_value("2"),
+        )
+        .arg(
+            Arg::new("pool.idle-timeout")
+                .long("pool.idle-timeout")
+                .help("Seconds after which an unused pooled connection is closed, 0 to keep connections open")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("300"),
+        )
+        .arg(
+            Arg::new("pool.timeout")
+                .long("pool.timeout")
+                .help("Seconds to wait for a pooled connection to become available or to be established")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("10"),
+        )
+        .arg(
+            Arg::new("pool.recycling-method")
+                .long("pool.recycling-method")
+                .help("Health check run before a pooled connection is reused: fast, verified or clean")
+                .value_parser(pool::parse_recycling_method)
+                .default_value("verified"),
+        )
+        .arg(
+            Arg::new("statements.limit")
+                .long("statements.limit")
+                .help("Number of statements with the highest total execution time exported from pg_stat_statements")
+                .value_parser(clap::value_parser!(i64).range(0..))
+                .default_value("100"),
+        )
+        .arg(
+            Arg::new("wraparound.table-limit")
+                .long("wraparound.table-limit")
+                .help("Number of tables with the oldest relfrozenxid exported from each database")
+                .value_parser(clap::value_parser!(i64).range(0..))
+                .default_value("10"),
         )
     .get_matches();
 }
 
-#[derive(Debug)]
-struct CommonEffectiveness {
-    relname: String,
-    seq_scan: i64,
-    seq_tup_read: i64,
-    idx_scan: i64,
-    avg: i64,
-    vacuum_full_count: i64,
-    autovacuum_count: i64,
-    analyze_count: i64,
-    autoanalyze_count: i64,
+#[derive(Clone)]
+struct AppState {
+    target: Arc<Target>,
+    prober: Arc<Prober>,
+    databases: Option<Arc<DatabaseFilter>>,
+    collector_options: Arc<CollectorOptions>,
 }
 
-#[derive(Debug)]
-struct IndexUsage {
-    relname: String,
-    percent_of_times_index_used: i64,
-    rows_in_table: i64,
+fn render(headers: &HeaderMap, families: &[MetricFamily]) -> Response {
+    let format = Format::from_accept(
+        headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok()),
+    );
+    let body = format.render(families);
+    ([(header::CONTENT_TYPE, format.content_type())], body).into_response()
 }
 
-#[derive(Debug)]
-struct HitMiss {
-    heap_read: Decimal,
-    heap_hit: Decimal,
-    ratio: Decimal,
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    render(
+        &headers,
+        &collectors::scrape(
+            &state.target,
+            state.databases.as_deref(),
+            &state.collector_options,
+        )
+        .await,
+    )
 }
 
-async fn metrics() -> String {
-    let (postgres_client, connection) = tokio_postgres::connect(
-        format!("host={} user={} password={} dbname={}",
-                ARGS.get_one::<String>("host").unwrap(),
-                ARGS.get_one::<String>("user").unwrap(),
-                ARGS.get_one::<String>("password").unwrap(),
-                ARGS.get_one::<String>("database").unwrap())
-            .as_str(),
-        NoTls,
-    )
-    .await
-    .unwrap();
-    tokio::spawn(async move {
-        if let Err(e) = connection.await {
-            eprintln!("connection error: {}", e);
-        }
-    });
-    let common_effectiveness_statement = postgres_client
-        .prepare(
-            "SELECT
-                relname,
-                seq_scan,
-                seq_tup_read,
-                idx_scan,
-                vacuum_count,
-                autovacuum_count,
-                analyze_count,
-                autoanalyze_count,
-                seq_tup_read/seq_scan as avg
-            FROM
-                pg_stat_user_tables
-            WHERE
-                seq_scan > 0
-            ORDER BY seq_tup_read DESC")
-        .await
-        .unwrap();
-    let common_effectiveness_rows = postgres_client
-        .query(&common_effectiveness_statement, &[])
-        .await
-        .unwrap();
-    let common_effectiveness: Vec<CommonEffectiveness> = common_effectiveness_rows
-        .iter()
-        .map(|row| CommonEffectiveness {
-            relname: row.get(0),
-            seq_scan: row.get(1),
-            seq_tup_read: row.get(2),
-            idx_scan: row.get(3),
-            vacuum_full_count: row.get(4),
-            autovacuum_count: row.get(5),
-            analyze_count: row.get(6),
-            autoanalyze_count: row.get(7),
-            avg: row.get(8),
-        })
-        .collect();
-    let hit_miss_statement = postgres_client
-        .prepare(
-            "SELECT
-                sum(heap_blks_read) as heap_read,
-                sum(heap_blks_hit) as heap_hit,
-                sum(heap_blks_hit) / (sum(heap_blks_hit) + sum(heap_blks_read)) as ratio
-            FROM pg_statio_user_tables")
-        .await
-        .unwrap();
-    let hit_miss_rows = postgres_client
-        .query(&hit_miss_statement, &[])
-        .await
-        .unwrap();
-    let hit_miss: Vec<HitMiss> = hit_miss_rows
-        .iter()
-        .map(|row| HitMiss {
-            heap_read: row.get(0),
-            heap_hit: row.get(1),
-            ratio: row.get(2),
-        })
-        .collect();
-    let index_usage_statement = postgres_client
-        .prepare(
-            "SELECT
-              relname,
-              100 * idx_scan / (seq_scan + idx_scan) percent_of_times_index_used,
-              n_live_tup rows_in_table
-            FROM
-              pg_stat_user_tables
-            WHERE
-                seq_scan + idx_scan > 0
-            ORDER BY
-              n_live_tup DESC;",
-            )
-            .await
-            .unwrap();
-    let index_usage_rows = postgres_client
-        .query(&index_usage_statement, &[])
-        .await
-        .unwrap();
-    let index_usage: Vec<IndexUsage> = index_usage_rows
-        .iter()
-        .map(|row| IndexUsage {
-            relname: row.get(0),
-            percent_of_times_index_used: row.get(1),
-            rows_in_table: row.get(2),
-        })
-        .collect();
-    let mut result = String::new();
-    for i in common_effectiveness {
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.seq_scan.{} {}\n",
-                i.relname, i.seq_scan
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.seq_tup_read.{} {}\n",
-                i.relname, i.seq_tup_read
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.idx_scan.{} {}\n",
-                i.relname, i.idx_scan
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.vacuum_full_count.{} {}\n",
-                i.relname, i.vacuum_full_count
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.autovacuum_count.{} {}\n",
-                i.relname, i.autovacuum_count
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.analyze_count.{} {}\n",
-                i.relname, i.analyze_count
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.autoanalyze_count.{}: {}\n",
-                i.relname, i.autoanalyze_count
+async fn probe(
+    State(state): State<AppState>,
+    Query(query): Query<ProbeQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let target = match query.target.filter(|target| !target.is_empty()) {
+        Some(target) => target,
+        None => return (StatusCode::BAD_REQUEST, "target parameter is missing").into_response(),
+    };
+    match state.prober.target(&target, query.auth_module.as_deref()) {
+        Ok(target) => render(
+            &headers,
+            &collectors::scrape(
+                &target,
+                state.databases.as_deref(),
+                &state.collector_options,
             )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.common_effectiveness.avg.{} {}\n",
-                i.relname, i.avg
-            )
-            .as_str();
+            .await,
+        ),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
     }
-    for i in hit_miss {
-        result = result
-            + format!(
-                "postgresql.hit_miss.heap_read {}\n",
-                i.heap_read
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.hit_miss.heap_hit {}\n",
-                i.heap_hit
-            )
-            .as_str();
-        result =
-            result + format!("postgresql.hit_miss.ratio {}\n", i.ratio).as_str();
+}
+
+/// Merges libpq environment variables, the connection string and the
+/// explicit command line arguments, later sources taking precedence.
+fn connection_params() -> Result<Params, String> {
+    let mut params = conninfo::from_env();
+    if let Some(dsn) = ARGS.get_one::<String>("dsn") {
+        params.extend(conninfo::parse(dsn)?);
     }
-    for i in index_usage {
-        result = result
-            + format!(
-                "postgresql.index_usage.percent_of_times_index_used.{} {}\n",
-                i.relname, i.percent_of_times_index_used
-            )
-            .as_str();
-        result = result
-            + format!(
-                "postgresql.index_usage.rows_in_table.{} {}\n",
-                i.relname, i.rows_in_table
-            )
-            .as_str();
+    for (arg, key) in [
+        ("host", "host"),
+        ("database", "dbname"),
+        ("user", "user"),
+        ("password", "password"),
+        ("sslmode", "sslmode"),
+        ("sslrootcert", "sslrootcert"),
+        ("sslcert", "sslcert"),
+        ("sslkey", "sslkey"),
+    ] {
+        if let Some(value) = ARGS.get_one::<String>(arg) {
+            params.insert(key.to_string(), value.clone());
+        }
     }
-    result
+    Ok(params)
+}
+
+fn parse_telemetry_path(value: &str) -> Result<String, String> {
+    if value.starts_with('/') && value != "/probe" {
+        Ok(value.to_string())
+    } else {
+        Err("telemetry path must start with '/' and differ from /probe".to_string())
+    }
+}
+
+fn exit_on_error<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
 }
 
 #[tokio::main]
 async fn main() {
-    let server = SocketAddr::from(([0, 0, 0, 0], 8080));
-    let app = Router::new().route("/metrics", axum::routing::get(metrics));
-    // Start server.
-    axum::Server::bind(&server)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    let params = exit_on_error(connection_params());
+    let password_file = ARGS.get_one::<String>("password-file").map(PathBuf::from);
+    let pool_options = PoolOptions {
+        max_size: *ARGS.get_one::<usize>("pool.max-size").unwrap(),
+        idle_timeout: Duration::from_secs(*ARGS.get_one::<u64>("pool.idle-timeout").unwrap()),
+        timeout: Duration::from_secs(*ARGS.get_one::<u64>("pool.timeout").unwrap()),
+        recycling_method: ARGS
+            .get_one::<RecyclingMethod>("pool.recycling-method")
+            .unwrap()
+            .clone(),
+    };
+    let target = exit_on_error(Target::new(
+        params.clone(),
+        password_file,
+        pool_options.clone(),
+    ));
+    let databases = if ARGS.get_flag("auto-discover-databases") {
+        Some(Arc::new(exit_on_error(DatabaseFilter::new(
+            ARGS.get_one::<String>("include-databases").map(String::as_str),
+            ARGS.get_one::<String>("exclude-databases").map(String::as_str),
+        ))))
+    } else {
+        None
+    };
+    let collector_options = CollectorOptions {
+        statements_limit: *ARGS.get_one::<i64>("statements.limit").unwrap(),
+        wraparound_table_limit: *ARGS.get_one::<i64>("wraparound.table-limit").unwrap(),
+    };
+    let probe_config = match ARGS.get_one::<String>("config.file") {
+        Some(path) => exit_on_error(probe::Config::load(path)),
+        None => probe::Config::default(),
+    };
+    let prober = Prober::new(probe_config, params, pool_options);
+    let app = Router::new()
+        .route(
+            ARGS.get_one::<String>("web.telemetry-path").unwrap(),
+            axum::routing::get(metrics),
+        )
+        .route("/probe", axum::routing::get(probe))
+        .with_state(AppState {
+            target: Arc::new(target),
+            prober: Arc::new(prober),
+            databases,
+            collector_options: Arc::new(collector_options),
+        });
+    // Bind every listen address before serving any, then run one server
+    // per address and exit as soon as one of them fails.
+    let listeners: Vec<_> = ARGS
+        .get_many::<ListenAddress>("web.listen-address")
+        .unwrap()
+        .map(|address| exit_on_error(listen::bind(address)))
+        .collect();
+    let mut servers = JoinSet::new();
+    for listener in listeners {
+        servers.spawn(listen::serve(listener, app.clone()));
+    }
+    while let Some(result) = servers.join_next().await {
+        exit_on_error(result.unwrap());
+    }
 }
@@ -0,0 +1,135 @@
+use crate::conninfo::{self, Params, PasswordSource};
+use crate::tls;
+use async_trait::async_trait;
+use deadpool::managed::{self, RecycleError, RecycleResult};
+use deadpool_postgres::{ClientWrapper, RecyclingMethod, Runtime};
+use postgres_native_tls::MakeTlsConnector;
+use std::fmt;
+use std::time::Duration;
+
+pub type Pool = managed::Pool<Manager>;
+pub type Client = managed::Object<Manager>;
+
+#[derive(Debug, Clone)]
+pub struct PoolOptions {
+    pub max_size: usize,
+    /// Connections unused for longer than this are closed. Zero keeps them forever.
+    pub idle_timeout: Duration,
+    /// Time to wait for a free connection or for a new one to be established.
+    pub timeout: Duration,
+    pub recycling_method: RecyclingMethod,
+}
+
+pub fn parse_recycling_method(value: &str) -> Result<RecyclingMethod, String> {
+    match value {
+        "fast" => Ok(RecyclingMethod::Fast),
+        "verified" => Ok(RecyclingMethod::Verified),
+        "clean" => Ok(RecyclingMethod::Clean),
+        _ => Err(format!(
+            "unknown recycling method '{}', expected fast, verified or clean",
+            value
+        )),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Password(String),
+    Postgres(tokio_postgres::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Password(e) => write!(f, "{}", e),
+            Error::Postgres(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for Error {
+    fn from(e: tokio_postgres::Error) -> Self {
+        Error::Postgres(e)
+    }
+}
+
+/// Creates connections the way `deadpool_postgres::Manager` does, but looks
+/// the password up again for every new connection so that rotated
+/// credentials are picked up without a restart.
+pub struct Manager {
+    pg_config: tokio_postgres::Config,
+    tls_connector: MakeTlsConnector,
+    password: PasswordSource,
+    recycling_method: RecyclingMethod,
+}
+
+#[async_trait]
+impl managed::Manager for Manager {
+    type Type = ClientWrapper;
+    type Error = Error;
+
+    async fn create(&self) -> Result<ClientWrapper, Error> {
+        let mut pg_config = self.pg_config.clone();
+        if let Some(password) = self.password.read().map_err(Error::Password)? {
+            pg_config.password(password);
+        }
+        let (client, connection) = pg_config.connect(self.tls_connector.clone()).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("connection error: {}", e);
+            }
+        });
+        Ok(ClientWrapper::new(client))
+    }
+
+    async fn recycle(&self, client: &mut ClientWrapper) -> RecycleResult<Error> {
+        if client.is_closed() {
+            return Err(RecycleError::StaticMessage("Connection closed"));
+        }
+        if let Some(sql) = self.recycling_method.query() {
+            client.simple_query(sql).await.map_err(Error::from)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn create_pool(
+    params: &Params,
+    password: PasswordSource,
+    options: PoolOptions,
+) -> Result<Pool, String> {
+    let manager = Manager {
+        pg_config: conninfo::to_config(params)?,
+        tls_connector: tls::make_tls_connector(&conninfo::tls_options(params)?)?,
+        password,
+        recycling_method: options.recycling_method,
+    };
+    let pool = Pool::builder(manager)
+        .max_size(options.max_size)
+        .runtime(Runtime::Tokio1)
+        .wait_timeout(Some(options.timeout))
+        .create_timeout(Some(options.timeout))
+        .recycle_timeout(Some(options.timeout))
+        .build()
+        .expect("failed to build connection pool");
+    if !options.idle_timeout.is_zero() {
+        spawn_idle_reaper(pool.clone(), options.idle_timeout);
+    }
+    Ok(pool)
+}
+
+/// Periodically drops pooled connections that have not been used for
+/// `idle_timeout`, so the exporter does not hold backends open between
+/// infrequent scrapes. Stops once the pool is closed.
+fn spawn_idle_reaper(pool: Pool, idle_timeout: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(idle_timeout / 2);
+        loop {
+            interval.tick().await;
+            if pool.is_closed() {
+                break;
+            }
+            pool.retain(|_, metrics| metrics.last_used() < idle_timeout);
+        }
+    });
+}
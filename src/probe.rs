@@ -0,0 +1,176 @@
+use crate::conninfo::{self, Params};
+use crate::pool::PoolOptions;
+use crate::target::Target;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Probed targets unused for this long are dropped with their pools.
+const TARGET_TTL: Duration = Duration::from_secs(600);
+
+/// Upper bound on cached probe targets; the least recently used one is
+/// dropped to make room for a new one.
+const MAX_TARGETS: usize = 100;
+
+/// Parameters a target may set when an auth module supplies the rest.
+const TARGET_KEYS: &[&str] = &["host", "port", "dbname"];
+
+/// Contents of the file given with `--config.file`.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub auth_modules: HashMap<String, AuthModule>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Config, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config file {}: {}", path, e))?;
+        serde_yaml::from_str(&contents)
+            .map_err(|e| format!("failed to parse config file {}: {}", path, e))
+    }
+}
+
+/// Named credentials a probe can refer to with `auth_module=<name>`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum AuthModule {
+    #[serde(rename = "userpass")]
+    UserPass {
+        userpass: UserPass,
+        /// Extra connection parameters such as `sslmode` or `application_name`.
+        #[serde(default)]
+        options: Params,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserPass {
+    pub username: String,
+    pub password: Option<String>,
+    pub password_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProbeQuery {
+    pub target: Option<String>,
+    pub auth_module: Option<String>,
+}
+
+/// Probed address and auth module.
+type TargetKey = (String, Option<String>);
+
+struct CachedTarget {
+    target: Arc<Target>,
+    last_used: Instant,
+}
+
+/// Hands out a `Target` per probed address and auth module, so that
+/// repeated probes of the same instance reuse their connections. Targets
+/// are forgotten after `TARGET_TTL` without probes, and at most
+/// `MAX_TARGETS` are kept.
+pub struct Prober {
+    config: Config,
+    default_params: Params,
+    pool_options: PoolOptions,
+    targets: Mutex<HashMap<TargetKey, CachedTarget>>,
+}
+
+impl Prober {
+    /// Probes without an auth module connect without credentials, taking
+    /// only non-credential settings such as `sslmode` from `default_params`.
+    pub fn new(config: Config, default_params: Params, pool_options: PoolOptions) -> Prober {
+        Prober {
+            config,
+            default_params,
+            pool_options,
+            targets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn target(&self, target: &str, auth_module: Option<&str>) -> Result<Arc<Target>, String> {
+        let key = (target.to_string(), auth_module.map(str::to_string));
+        let mut targets = self.targets.lock().unwrap();
+        let now = Instant::now();
+        targets.retain(|_, cached| now.duration_since(cached.last_used) < TARGET_TTL);
+        if let Some(cached) = targets.get_mut(&key) {
+            cached.last_used = now;
+            return Ok(cached.target.clone());
+        }
+        let target_params = target_params(target)?;
+        let (mut params, password_file, password_lookup) = match auth_module {
+            Some(name) => {
+                let AuthModule::UserPass { userpass, options } = self
+                    .config
+                    .auth_modules
+                    .get(name)
+                    .ok_or_else(|| format!("unknown auth_module '{}'", name))?;
+                // Anything but the address would let the caller override the
+                // module's credentials or weaken its TLS settings.
+                if let Some(key) = target_params
+                    .keys()
+                    .find(|key| !TARGET_KEYS.contains(&key.as_str()))
+                {
+                    return Err(format!(
+                        "target must not set '{}' when an auth_module is used",
+                        key
+                    ));
+                }
+                let mut params = options.clone();
+                params.insert("user".to_string(), userpass.username.clone());
+                if let Some(password) = &userpass.password {
+                    params.insert("password".to_string(), password.clone());
+                }
+                (params, userpass.password_file.clone(), true)
+            }
+            None => {
+                // The exporter's own credentials must never be sent to a
+                // caller-chosen host: only settings such as sslmode carry over,
+                // and a password has to be part of the target itself.
+                let mut params = self.default_params.clone();
+                for key in [
+                    "host", "port", "dbname", "user", "password", "passfile", "sslcert", "sslkey",
+                ] {
+                    params.remove(key);
+                }
+                (params, None, false)
+            }
+        };
+        params.extend(target_params);
+        let target = if password_lookup {
+            Target::new(params, password_file, self.pool_options.clone())?
+        } else {
+            Target::without_password_lookup(params, self.pool_options.clone())?
+        };
+        let target = Arc::new(target);
+        if targets.len() >= MAX_TARGETS {
+            let oldest = targets
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                targets.remove(&oldest);
+            }
+        }
+        targets.insert(
+            key,
+            CachedTarget {
+                target: target.clone(),
+                last_used: now,
+            },
+        );
+        Ok(target)
+    }
+}
+
+/// Accepts `host:port/dbname` as well as full `postgresql://` URIs.
+fn target_params(target: &str) -> Result<Params, String> {
+    if target.starts_with("postgresql://") || target.starts_with("postgres://") {
+        conninfo::parse(target)
+    } else {
+        conninfo::parse(&format!("postgresql://{}", target))
+    }
+}
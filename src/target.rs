@@ -0,0 +1,95 @@
+use crate::conninfo::{Params, PasswordSource};
+use crate::pool::{self, Pool, PoolOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A PostgreSQL server the exporter scrapes, with a pool for the configured
+/// database and lazily created pools for the other databases of the cluster.
+pub struct Target {
+    params: Params,
+    password_file: Option<PathBuf>,
+    /// Whether the libpq password file is searched when neither `password`
+    /// nor `password_file` is set.
+    pgpass: bool,
+    pool_options: PoolOptions,
+    pool: Pool,
+    database_pools: Mutex<HashMap<String, Pool>>,
+}
+
+impl Target {
+    pub fn new(
+        params: Params,
+        password_file: Option<PathBuf>,
+        pool_options: PoolOptions,
+    ) -> Result<Target, String> {
+        Target::create(params, password_file, true, pool_options)
+    }
+
+    /// A target that only uses the password given in `params`, never a
+    /// password file.
+    pub fn without_password_lookup(
+        params: Params,
+        pool_options: PoolOptions,
+    ) -> Result<Target, String> {
+        Target::create(params, None, false, pool_options)
+    }
+
+    fn create(
+        params: Params,
+        password_file: Option<PathBuf>,
+        pgpass: bool,
+        pool_options: PoolOptions,
+    ) -> Result<Target, String> {
+        let password = password_source(&params, password_file.as_deref(), pgpass);
+        let pool = pool::create_pool(&params, password, pool_options.clone())?;
+        Ok(Target {
+            params,
+            password_file,
+            pgpass,
+            pool_options,
+            pool,
+            database_pools: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn pool(&self) -> &Pool {
+        &self.pool
+    }
+
+    /// Pool for another database on the same server, using the same
+    /// credentials and TLS settings.
+    pub fn database_pool(&self, dbname: &str) -> Result<Pool, String> {
+        if self.params.get("dbname").map(String::as_str) == Some(dbname) {
+            return Ok(self.pool.clone());
+        }
+        let mut database_pools = self.database_pools.lock().unwrap();
+        if let Some(pool) = database_pools.get(dbname) {
+            return Ok(pool.clone());
+        }
+        let mut params = self.params.clone();
+        params.insert("dbname".to_string(), dbname.to_string());
+        let password = password_source(&params, self.password_file.as_deref(), self.pgpass);
+        let pool = pool::create_pool(&params, password, self.pool_options.clone())?;
+        database_pools.insert(dbname.to_string(), pool.clone());
+        Ok(pool)
+    }
+}
+
+impl Drop for Target {
+    /// Closes the pools so that their connections and idle reapers go away
+    /// with the target.
+    fn drop(&mut self) {
+        self.pool.close();
+        for pool in self.database_pools.get_mut().unwrap().values() {
+            pool.close();
+        }
+    }
+}
+
+fn password_source(params: &Params, password_file: Option<&Path>, pgpass: bool) -> PasswordSource {
+    match PasswordSource::for_params(params, password_file) {
+        PasswordSource::PgPass(_) if !pgpass => PasswordSource::Params,
+        source => source,
+    }
+}
@@ -0,0 +1,108 @@
+use native_tls::{Certificate, Identity, TlsConnector};
+use postgres_native_tls::MakeTlsConnector;
+use std::fs;
+use std::path::PathBuf;
+
+/// libpq-compatible `sslmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslMode {
+    Disable,
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl SslMode {
+    pub fn to_postgres(self) -> tokio_postgres::config::SslMode {
+        match self {
+            SslMode::Disable => tokio_postgres::config::SslMode::Disable,
+            SslMode::Prefer => tokio_postgres::config::SslMode::Prefer,
+            SslMode::Require | SslMode::VerifyCa | SslMode::VerifyFull => {
+                tokio_postgres::config::SslMode::Require
+            }
+        }
+    }
+}
+
+pub fn parse_ssl_mode(value: &str) -> Result<SslMode, String> {
+    match value {
+        "disable" => Ok(SslMode::Disable),
+        "prefer" => Ok(SslMode::Prefer),
+        "require" => Ok(SslMode::Require),
+        "verify-ca" => Ok(SslMode::VerifyCa),
+        "verify-full" => Ok(SslMode::VerifyFull),
+        _ => Err(format!(
+            "unknown sslmode '{}', expected disable, prefer, require, verify-ca or verify-full",
+            value
+        )),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsOptions {
+    pub ssl_mode: SslMode,
+    pub root_cert: Option<String>,
+    pub cert: Option<String>,
+    pub key: Option<String>,
+}
+
+/// Root certificate file libpq uses when `sslrootcert` is not set.
+fn default_root_cert() -> Option<PathBuf> {
+    Some(PathBuf::from(std::env::var_os("HOME")?).join(".postgresql/root.crt"))
+}
+
+/// Builds a TLS connector that verifies the server the way libpq does for
+/// the given `sslmode`: `prefer` and `require` only encrypt, unless a root
+/// certificate is available, in which case `require` behaves like
+/// `verify-ca`; `verify-ca` checks the chain, `verify-full` also the host name.
+/// As in libpq, the root certificates in `sslrootcert` (by default
+/// `~/.postgresql/root.crt`) are the only trusted ones; the system store is
+/// never consulted.
+pub fn make_tls_connector(options: &TlsOptions) -> Result<MakeTlsConnector, String> {
+    let mut builder = TlsConnector::builder();
+    let root_cert = match &options.root_cert {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_root_cert().filter(|path| path.exists()),
+    };
+    let verify_chain = match options.ssl_mode {
+        SslMode::Disable | SslMode::Prefer => false,
+        SslMode::Require => root_cert.is_some(),
+        SslMode::VerifyCa | SslMode::VerifyFull => true,
+    };
+    builder
+        .danger_accept_invalid_certs(!verify_chain)
+        .danger_accept_invalid_hostnames(options.ssl_mode != SslMode::VerifyFull);
+    if verify_chain {
+        let path = root_cert.ok_or_else(|| {
+            "server certificate verification requires a root certificate: \
+             set sslrootcert, create ~/.postgresql/root.crt or change sslmode"
+                .to_string()
+        })?;
+        let pem = fs::read(&path)
+            .map_err(|e| format!("failed to read sslrootcert {}: {}", path.display(), e))?;
+        let certs = Certificate::stack_from_pem(&pem)
+            .map_err(|e| format!("failed to parse sslrootcert {}: {}", path.display(), e))?;
+        builder.disable_built_in_roots(true);
+        for cert in certs {
+            builder.add_root_certificate(cert);
+        }
+    }
+    match (&options.cert, &options.key) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = fs::read(cert_path)
+                .map_err(|e| format!("failed to read sslcert {}: {}", cert_path, e))?;
+            let key = fs::read(key_path)
+                .map_err(|e| format!("failed to read sslkey {}: {}", key_path, e))?;
+            let identity = Identity::from_pkcs8(&cert, &key)
+                .map_err(|e| format!("failed to load client certificate: {}", e))?;
+            builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => return Err("sslcert and sslkey must be given together".to_string()),
+    }
+    let connector = builder
+        .build()
+        .map_err(|e| format!("failed to build TLS connector: {}", e))?;
+    Ok(MakeTlsConnector::new(connector))
+}